@@ -0,0 +1,212 @@
+//! Detecting bulk changes, like switching branches, and holding their file events until they're
+//! finished.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::event_filter::FileEvent;
+
+/// Files in the Git directory which are changed by operations like `git checkout` and `git rebase`.
+///
+/// The index lock isn't included, because it's also written by read-only commands like
+/// `git status`.
+const GIT_ACTIVITY_FILES: [&str; 2] = ["HEAD", "ORIG_HEAD"];
+
+/// Options for detecting bulk changes.
+#[derive(Debug, Clone)]
+pub struct BulkChangeOpts {
+    /// A batch of at least this many file events is considered a bulk change.
+    pub threshold: usize,
+    /// A bulk change is finished once no file events have arrived for this long.
+    pub quiet_period: Duration,
+    /// The Git directory to watch for activity, if any.
+    pub git_dir: Option<GitDir>,
+}
+
+/// Holds file events during bulk changes, like `git checkout` or `git rebase`, so that they can
+/// be handled all at once instead of as a cascade of interrupted reloads.
+///
+/// A bulk change starts when a batch of file events is large enough, or when the Git `HEAD` or
+/// `ORIG_HEAD` changes. It finishes once no file events have arrived for the quiet period and the
+/// Git index isn't locked.
+#[derive(Debug)]
+pub struct BulkChange {
+    opts: BulkChangeOpts,
+    /// Events held during a bulk change, if one is in progress.
+    held: Option<BTreeSet<FileEvent>>,
+    /// When the bulk change in progress will be finished, if no more events arrive.
+    deadline: Instant,
+}
+
+impl BulkChange {
+    /// Construct a new bulk change detector.
+    pub fn new(opts: BulkChangeOpts) -> Self {
+        Self {
+            opts,
+            held: None,
+            deadline: Instant::now(),
+        }
+    }
+
+    /// Add a batch of file events.
+    ///
+    /// Returns the events if they should be handled now, or `None` if they're held until the bulk
+    /// change finishes.
+    pub fn add(
+        &mut self,
+        events: BTreeSet<FileEvent>,
+        git_activity: bool,
+    ) -> Option<BTreeSet<FileEvent>> {
+        if self.held.is_none() {
+            if git_activity {
+                tracing::info!("Git activity detected, waiting for changes to finish");
+            } else if events.len() >= self.opts.threshold {
+                tracing::info!(
+                    "{} files changed at once, waiting for changes to finish",
+                    events.len()
+                );
+            } else {
+                return Some(events);
+            }
+        }
+
+        self.held.get_or_insert_with(BTreeSet::new).extend(events);
+        self.deadline = Instant::now() + self.opts.quiet_period;
+        None
+    }
+
+    /// Wait for the bulk change in progress to finish, and return its events.
+    ///
+    /// If there's no bulk change in progress, this never completes.
+    pub async fn finished(&mut self) -> BTreeSet<FileEvent> {
+        if self.held.is_none() {
+            return std::future::pending().await;
+        }
+
+        loop {
+            tokio::time::sleep_until(self.deadline).await;
+            if self.index_is_locked() {
+                self.deadline = Instant::now() + self.opts.quiet_period;
+            } else {
+                break;
+            }
+        }
+
+        let events = self.held.take().unwrap_or_default();
+        tracing::info!("Changes finished, {} files changed", events.len());
+        events
+    }
+
+    fn index_is_locked(&self) -> bool {
+        self.opts
+            .git_dir
+            .as_ref()
+            .is_some_and(|git_dir| git_dir.index_is_locked())
+    }
+}
+
+/// A Git directory, usually `.git`.
+#[derive(Debug, Clone)]
+pub struct GitDir(PathBuf);
+
+impl GitDir {
+    /// Find the Git directory for the given directory, if it's in a Git repository.
+    pub fn find(dir: &Path) -> Option<Self> {
+        for dir in dir.ancestors() {
+            let git = dir.join(".git");
+            if git.is_dir() {
+                return Some(Self(git));
+            } else if git.is_file() {
+                // In worktrees and submodules, `.git` is a file pointing to the Git directory.
+                let contents = std::fs::read_to_string(&git).ok()?;
+                let git_dir = contents.strip_prefix("gitdir:")?.trim();
+                return Some(Self(dir.join(git_dir)));
+            }
+        }
+        None
+    }
+
+    /// Get the path to this Git directory.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Is this path in the Git directory?
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.0)
+    }
+
+    /// Is this path one of the files in the Git directory which signal a bulk change?
+    pub fn is_activity(&self, path: &Path) -> bool {
+        path.parent() == Some(self.0.as_path())
+            && path
+                .file_name()
+                .is_some_and(|name| GIT_ACTIVITY_FILES.iter().any(|file| name == *file))
+    }
+
+    fn index_is_locked(&self) -> bool {
+        self.0.join("index.lock").exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use camino::Utf8PathBuf;
+
+    fn modify(paths: &[&str]) -> BTreeSet<FileEvent> {
+        paths
+            .iter()
+            .map(|path| FileEvent::Modify(Utf8PathBuf::from(*path)))
+            .collect()
+    }
+
+    #[test]
+    fn test_git_dir() {
+        let git_dir = GitDir(PathBuf::from("/project/.git"));
+        assert!(git_dir.is_activity(Path::new("/project/.git/HEAD")));
+        assert!(git_dir.is_activity(Path::new("/project/.git/ORIG_HEAD")));
+        assert!(!git_dir.is_activity(Path::new("/project/.git/index.lock")));
+        assert!(!git_dir.is_activity(Path::new("/project/.git/config")));
+        assert!(!git_dir.is_activity(Path::new("/project/.git/refs/heads/HEAD")));
+        assert!(git_dir.contains(Path::new("/project/.git/config")));
+        assert!(!git_dir.contains(Path::new("/project/src/MyLib.hs")));
+    }
+
+    #[test]
+    fn test_bulk_change() {
+        let mut bulk_change = BulkChange::new(BulkChangeOpts {
+            threshold: 3,
+            quiet_period: Duration::from_secs(1),
+            git_dir: None,
+        });
+
+        // Small batches are handled immediately.
+        assert_eq!(
+            bulk_change.add(modify(&["A.hs", "B.hs"]), false),
+            Some(modify(&["A.hs", "B.hs"]))
+        );
+
+        // Large batches start a bulk change, and later batches are held too.
+        assert_eq!(
+            bulk_change.add(modify(&["A.hs", "B.hs", "C.hs"]), false),
+            None
+        );
+        assert_eq!(bulk_change.add(modify(&["D.hs"]), false), None);
+        assert_eq!(
+            bulk_change.held,
+            Some(modify(&["A.hs", "B.hs", "C.hs", "D.hs"]))
+        );
+
+        // Git activity starts a bulk change.
+        let mut bulk_change = BulkChange::new(bulk_change.opts);
+        assert_eq!(bulk_change.add(BTreeSet::new(), true), None);
+        assert_eq!(bulk_change.add(modify(&["A.hs"]), false), None);
+        assert_eq!(bulk_change.held, Some(modify(&["A.hs"])));
+    }
+}
@@ -48,6 +48,7 @@ impl TypedValueParser for DurationValueParser {
     }
 }
 
+#[allow(dead_code)]
 struct DurationValueParserFactory;
 
 impl ValueParserFactory for DurationValueParserFactory {
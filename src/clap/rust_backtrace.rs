@@ -43,6 +43,7 @@ impl Display for RustBacktrace {
     }
 }
 
+#[allow(dead_code)]
 struct RustBacktraceParserFactory;
 
 impl ValueParserFactory for RustBacktraceParserFactory {
@@ -127,13 +127,82 @@ where
             writeln!(self.writer)?;
         }
 
-        assert!(
-            self.command
-                .get_subcommands()
-                .collect::<Vec<_>>()
-                .is_empty(),
-            "Documenting subcommands is unsupported"
-        );
+        //----------------------------------
+        // Subcommands
+        //----------------------------------
+
+        self.write_subcommands()?;
+
+        Ok(())
+    }
+
+    /// Write a section for each subcommand, with its description, usage, and arguments.
+    ///
+    /// Nested subcommands are unsupported.
+    fn write_subcommands(&mut self) -> fmt::Result {
+        // Build the command so that subcommand usage includes the parent command's name.
+        let mut command = self.command.clone();
+        command.build();
+
+        let subcommands: Vec<_> = command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .collect();
+
+        if subcommands.is_empty() {
+            return Ok(());
+        }
+
+        self.state.section = None;
+        writeln!(self.writer, "## Subcommands\n")?;
+
+        for subcommand in subcommands {
+            assert!(
+                subcommand.get_subcommands().next().is_none(),
+                "Documenting nested subcommands is unsupported"
+            );
+
+            writeln!(
+                self.writer,
+                "### `{} {}`\n",
+                command.get_name(),
+                subcommand.get_name()
+            )?;
+
+            if let Some(about) = subcommand
+                .get_long_about()
+                .or_else(|| subcommand.get_about())
+            {
+                writeln!(self.writer, "{about}\n")?;
+            }
+
+            let usage = subcommand
+                .clone()
+                .render_usage()
+                .to_string()
+                .replace("Usage: ", "");
+            writeln!(self.writer, "**Usage:** `{usage}`\n")?;
+
+            let args: Vec<_> = subcommand
+                .get_arguments()
+                .filter(|arg| {
+                    !arg.is_hide_set()
+                        && !matches!(
+                            arg.get_action(),
+                            ArgAction::Help
+                                | ArgAction::HelpShort
+                                | ArgAction::HelpLong
+                                | ArgAction::Version
+                        )
+                })
+                .collect();
+
+            for arg in args {
+                self.write_arg_markdown(arg)?;
+            }
+
+            self.end_description_list()?;
+        }
 
         Ok(())
     }
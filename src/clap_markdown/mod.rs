@@ -17,7 +17,8 @@
 //! - Arguments are wrapped in [`<a id="...">`][anchor] links so that other parts
 //!   of the manual can link to specific arguments.
 //!
-//! - Support for documenting subcommands has been removed, as it's not used here.
+//! - Subcommands are documented in a single "Subcommands" section, and nested
+//!   subcommands are unsupported.
 //!
 //! This portion of the code (files in this directory) are Apache-2.0 or MIT licensed.
 //!
@@ -10,8 +10,11 @@ use tracing_subscriber::fmt::format::FmtSpan;
 use crate::clap::FmtSpanParserFactory;
 use crate::clap::RustBacktrace;
 use crate::clonable_command::ClonableCommand;
+use crate::ghci::SpecModuleFormat;
 use crate::ignore::GlobMatcher;
+use crate::ignore::IgnoreFiles;
 use crate::normal_path::NormalPath;
+use crate::path_map::PathMapping;
 
 /// An experimental feature that can be enabled with `--experimental-features`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
@@ -31,7 +34,8 @@ pub enum ExperimentalFeature {
     verbatim_doc_comment,
     max_term_width = 100,
     long_about = include_str!("cli-about.txt"),
-    override_usage = "ghciwatch [--command SHELL_COMMAND] [--watch PATH] [OPTIONS ...]"
+    override_usage = "ghciwatch [--command SHELL_COMMAND] [--watch PATH] [OPTIONS ...]",
+    disable_help_subcommand = true
 )]
 pub struct Opts {
     /// A shell command which starts a GHCi REPL, e.g. `ghci` or `cabal v2-repl` or similar.
@@ -57,10 +61,102 @@ pub struct Opts {
     /// Evaluate Haskell code in comments.
     ///
     /// This parses line commands starting with `-- $>` or multiline commands delimited by `{- $>`
-    /// and `<$ -}` and evaluates them after reloads.
+    /// and `<$ -}` and evaluates them after reloads. If the following lines start with `-- <$`,
+    /// they give the command's expected output, and an error is written to the error log if the
+    /// output doesn't match.
     #[arg(long, alias = "allow-eval")]
     pub enable_eval: bool,
 
+    /// The marker starting single-line eval commands.
+    #[arg(
+        long,
+        default_value = "-- $>",
+        value_name = "MARKER",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+    )]
+    pub eval_line_marker: String,
+
+    /// The marker starting multi-line eval commands.
+    #[arg(
+        long,
+        default_value = "{- $>",
+        value_name = "MARKER",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+    )]
+    pub eval_multiline_start_marker: String,
+
+    /// The marker ending multi-line eval commands.
+    #[arg(
+        long,
+        default_value = "<$ -}",
+        value_name = "MARKER",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+    )]
+    pub eval_multiline_end_marker: String,
+
+    /// The marker starting each line of an eval command's expected output.
+    ///
+    /// Doctest `-- >>>` examples always use plain comment lines for their output.
+    #[arg(
+        long,
+        default_value = "-- <$",
+        value_name = "MARKER",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+    )]
+    pub eval_output_marker: String,
+
+    /// Evaluate Haddock `-- >>>` examples as well, and check their output.
+    ///
+    /// Each example's expected output is given by the comment lines following it, up to the next
+    /// example or a blank comment line; `<BLANKLINE>` stands for a blank line in the output. If
+    /// the output doesn't match, an error is written to the error log.
+    #[arg(long, requires = "enable_eval")]
+    pub eval_doctests: bool,
+
+    /// Rewrite the expected output of eval commands in place when it doesn't match.
+    ///
+    /// This updates the `-- <$` lines after eval commands (and the output of `-- >>>` examples
+    /// with `--eval-doctests`) with the actual output, like updating golden tests, instead of
+    /// reporting errors. Eval commands without expected output are left alone.
+    #[arg(long, requires = "enable_eval")]
+    pub update_eval_output: bool,
+
+    /// Write the output of every eval command into the file beneath it, like `ghcid`'s
+    /// `--allow-eval`.
+    ///
+    /// The output is written as `-- <$` lines after each eval command (and as the output of
+    /// `-- >>>` examples with `--eval-doctests`), replacing any output written there before.
+    /// Writing the output doesn't trigger a reload.
+    #[arg(long, requires = "enable_eval")]
+    pub write_eval_output: bool,
+
+    /// Run eval commands in every file after every reload.
+    ///
+    /// By default, after a reload, eval commands are only run in modules which were recompiled or
+    /// which import a recompiled module.
+    #[arg(long, requires = "enable_eval")]
+    pub eval_all: bool,
+
+    /// A file to write the results of eval commands to, as JSON.
+    ///
+    /// The file contains an array with the latest result of each eval command, with its `path`,
+    /// `line`, `column`, `command`, `output`, and `status` (`ok`, `exception`, `error`, or
+    /// `timeout`). It's rewritten after eval commands are run.
+    #[arg(long, value_name = "PATH", requires = "enable_eval")]
+    pub eval_results_file: Option<Utf8PathBuf>,
+
+    /// Interrupt eval commands which take longer than the given duration.
+    ///
+    /// Timed-out eval commands are interrupted with `Ctrl-C` and reported as errors in the error
+    /// log, and the remaining eval commands and test hooks are run as usual.
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = crate::clap::DurationValueParser::default(),
+        requires = "enable_eval"
+    )]
+    pub eval_timeout: Option<Duration>,
+
     /// An extra directory for converting module paths to module names and vice versa, in
     /// addition to the module import search paths from GHCi's `:show paths` output.
     ///
@@ -73,6 +169,17 @@ pub struct Opts {
     #[arg(long = "extra-module-search-path", value_name = "PATH")]
     pub extra_module_search_paths: Vec<NormalPath>,
 
+    /// Map a directory as GHCi sees it to the same directory as `ghciwatch` sees it.
+    ///
+    /// Use this when GHCi runs in a container or VM with the project mounted at a different
+    /// path, like `--path-map /workspace=.`. Paths in GHCi's output (like search paths and
+    /// compiler diagnostics) are mapped to host paths, and paths in commands sent to GHCi are
+    /// mapped back.
+    ///
+    /// Can be given multiple times.
+    #[arg(long = "path-map", value_name = "CONTAINER=HOST")]
+    pub path_maps: Vec<PathMapping>,
+
     /// Clear the screen before reloads and restarts.
     #[arg(long)]
     pub clear: bool,
@@ -113,6 +220,10 @@ pub struct Opts {
     #[command(flatten)]
     pub hooks: crate::hooks::HookOpts,
 
+    /// Options for running tests.
+    #[command(flatten)]
+    pub test: TestOpts,
+
     /// Options to modify file watching.
     #[command(flatten)]
     pub watch: WatchOpts,
@@ -120,6 +231,80 @@ pub struct Opts {
     /// Options to modify logging and error-handling behavior.
     #[command(flatten)]
     pub logging: LoggingOpts,
+
+    /// A subcommand to run instead of watching for changes.
+    #[command(subcommand)]
+    pub subcommand: Option<Subcommand>,
+}
+
+/// Subcommands which run instead of watching for changes.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum Subcommand {
+    /// Show how changes to the given paths would be handled.
+    ///
+    /// This starts a GHCi session to find the loaded modules, and then prints whether a change to
+    /// each path would restart the session, reload it, add or remove a module, or be ignored,
+    /// along with the glob or built-in rule which decided it.
+    ///
+    /// Each path is explained as if it had just been modified, or removed if it doesn't exist.
+    /// Filters the file watcher applies before classifying changes aren't applied:
+    /// `--skip-unchanged` (there are no earlier contents to compare with), `--hold-bulk-changes`,
+    /// and listing the files in newly created directories (a directory is explained as a single
+    /// path).
+    ///
+    /// Options like `--reload-glob` and `--restart-glob` must be given before `explain`.
+    Explain {
+        /// The paths to explain.
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<NormalPath>,
+    },
+}
+
+/// Options for running tests.
+#[derive(Debug, Clone, clap::Args)]
+#[clap(next_help_heading = "Test options")]
+pub struct TestOpts {
+    /// After reloads, only run the tests for the modules which were recompiled.
+    ///
+    /// Before the test hooks run, each recompiled module is mapped to its spec module with
+    /// `--spec-module-format`, and the loaded spec modules are selected with `:set args --match
+    /// /Foo.Bar/ ...`, following `hspec-discover`'s naming. After startup, restarts, and reloads
+    /// which don't recompile any modules, the full test suite is run. If a reload recompiles
+    /// modules but doesn't affect any loaded spec modules, the test hooks are skipped.
+    ///
+    /// This overrides arguments set with `:set args` in other hooks.
+    #[arg(long)]
+    pub select_tests: bool,
+
+    /// The naming convention for spec modules with `--select-tests`, where `{module}` is the name
+    /// of the module being tested.
+    #[arg(
+        long,
+        default_value = "{module}Spec",
+        value_name = "FORMAT",
+        requires = "select_tests"
+    )]
+    pub spec_module_format: String,
+
+    /// Interrupt running tests when files change.
+    ///
+    /// When a file changes while the test hooks are running, the tests are interrupted with
+    /// `Ctrl-C` and the session is reloaded right away, rather than waiting for the test suite to
+    /// finish. This applies to tests run after reloads and restarts, even with
+    /// `--no-interrupt-reloads`.
+    #[arg(long)]
+    pub interrupt_tests: bool,
+}
+
+impl TestOpts {
+    /// Get the format for selecting spec modules, if `--select-tests` is given.
+    pub fn test_selection(&self) -> eyre::Result<Option<SpecModuleFormat>> {
+        if self.select_tests {
+            SpecModuleFormat::new(&self.spec_module_format).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 /// Options for watching files.
@@ -153,6 +338,16 @@ pub struct WatchOpts {
     #[arg(long = "watch", value_name = "PATH")]
     pub paths: Vec<NormalPath>,
 
+    /// Watch GHCi's module search paths (from `:show paths`) and the `.cabal` and `package.yaml`
+    /// files of their packages.
+    ///
+    /// The watched paths are updated whenever the search paths change, like after a restart.
+    /// Search paths in build directories (`dist-newstyle` and `.stack-work`) aren't watched.
+    ///
+    /// When this is given, `src` isn't watched by default.
+    #[arg(long)]
+    pub watch_search_paths: bool,
+
     /// Reload the GHCi session when paths matching this glob change.
     ///
     /// By default, only changes to Haskell source files trigger reloads. If you'd like to exclude
@@ -179,6 +374,90 @@ pub struct WatchOpts {
     /// [1]: https://gitlab.haskell.org/ghc/ghc/-/issues/11596
     #[arg(long = "restart-glob")]
     pub restart_globs: Vec<String>,
+
+    /// Don't reload when Haskell source files are saved without changing their contents.
+    ///
+    /// With this option, changes to Haskell source files are ignored if their contents are
+    /// unchanged (like when `touch`ing a file or saving an unmodified buffer). The watched source
+    /// files are read and hashed at startup.
+    #[arg(long)]
+    pub skip_unchanged: bool,
+
+    /// Hold file events during bulk changes, like switching branches, and handle them all at
+    /// once.
+    ///
+    /// A bulk change starts when `--bulk-change-threshold` files change at once, or when the Git
+    /// `HEAD` or `ORIG_HEAD` changes (like from `git checkout` or `git rebase`), unless
+    /// `--no-git-detection` is given. File events are then held until no files have changed for
+    /// `--bulk-change-quiet-period`.
+    #[arg(long)]
+    pub hold_bulk_changes: bool,
+
+    /// With `--hold-bulk-changes`, consider this many files changing at once to be a bulk change.
+    #[arg(
+        long,
+        default_value = "100",
+        value_name = "FILES",
+        requires = "hold_bulk_changes"
+    )]
+    pub bulk_change_threshold: usize,
+
+    /// With `--hold-bulk-changes`, how long to wait for files to stop changing before handling a
+    /// bulk change.
+    #[arg(
+        long,
+        default_value = "1s",
+        value_name = "DURATION",
+        value_parser = crate::clap::DurationValueParser::default(),
+        requires = "hold_bulk_changes",
+    )]
+    pub bulk_change_quiet_period: Duration,
+
+    /// With `--hold-bulk-changes`, don't watch the Git directory for operations like `git
+    /// checkout` to detect bulk changes.
+    ///
+    /// Bulk changes are then only detected with `--bulk-change-threshold`.
+    #[arg(long, requires = "hold_bulk_changes")]
+    pub no_git_detection: bool,
+
+    /// Ignore changes to paths ignored by `.gitignore`, `.ignore`, or `.ghciwatchignore` files.
+    ///
+    /// Changes to ignored paths then never trigger reloads or restarts, unless they match a
+    /// `--reload-glob` or `--restart-glob`. Changes to the ignore files themselves are noticed
+    /// even if they're outside the watched paths.
+    #[arg(long)]
+    pub respect_ignore_files: bool,
+
+    /// Listen for file save notifications from your editor on a Unix socket at this path.
+    ///
+    /// Each line written to the socket should be the path of a file which was saved or deleted,
+    /// either absolute or relative to the working directory. For example, `echo src/MyLib.hs |
+    /// nc -U .ghciwatch.sock`. Notifications are debounced like file events.
+    ///
+    /// This is useful on network filesystems or in VMs, where filesystem notifications are
+    /// unreliable and polling is expensive.
+    #[arg(long, value_name = "PATH")]
+    pub save_socket: Option<Utf8PathBuf>,
+
+    /// Don't watch the filesystem for changes, and only reload in response to save notifications
+    /// from `--save-socket`.
+    #[arg(long, requires = "save_socket")]
+    pub no_file_watcher: bool,
+
+    /// Record every batch of file events sent to GHCi to this file, for reproducing bugs with
+    /// `--replay-events`.
+    ///
+    /// Each line is a JSON object with the time the batch was sent (in milliseconds since GHCi
+    /// first finished loading) and the changed paths.
+    #[arg(long, value_name = "PATH")]
+    pub record_events: Option<Utf8PathBuf>,
+
+    /// Replay file events recorded with `--record-events` instead of watching for changes.
+    ///
+    /// Events are sent with the same timing as they were recorded, starting once GHCi first
+    /// finishes loading. `ghciwatch` keeps running after all events are replayed.
+    #[arg(long, value_name = "PATH", conflicts_with = "save_socket")]
+    pub replay_events: Option<Utf8PathBuf>,
 }
 
 impl WatchOpts {
@@ -191,6 +470,15 @@ impl WatchOpts {
     pub fn restart_globs(&self) -> eyre::Result<GlobMatcher> {
         GlobMatcher::from_globs(self.restart_globs.iter())
     }
+
+    /// Get the ignore files to respect when classifying changed paths, if any.
+    pub fn ignore_files(&self) -> Option<IgnoreFiles> {
+        if self.respect_ignore_files {
+            Some(IgnoreFiles::default())
+        } else {
+            None
+        }
+    }
 }
 
 // TODO: Possibly set `RUST_LIB_BACKTRACE` from `RUST_BACKTRACE` as well, so that `full`
@@ -264,7 +552,7 @@ impl Opts {
     pub fn init(&mut self) -> eyre::Result<()> {
         if let Some(file) = &self.file {
             self.watch.paths.push(file.clone());
-        } else if self.watch.paths.is_empty() {
+        } else if self.watch.paths.is_empty() && !self.watch.watch_search_paths {
             self.watch.paths.push(NormalPath::from_cwd("src")?);
         }
 
@@ -14,6 +14,7 @@ use eyre::Context;
 use tokio::process::Command;
 
 use crate::command_ext::CommandExt;
+use crate::placeholders::Placeholders;
 
 /// Like [`std::process::Stdio`], but it implements [`Clone`].
 ///
@@ -103,6 +104,20 @@ impl ClonableCommand {
         self
     }
 
+    /// Expand [`Placeholders`] like `{changed_files}` in this command's program and arguments.
+    ///
+    /// Like the arguments, the program isn't run through a shell, so it's not quoted.
+    pub fn expand(&self, placeholders: &Placeholders) -> Self {
+        Self {
+            program: match self.program.to_str() {
+                Some(program) => placeholders.expand_unquoted(program).into(),
+                None => self.program.clone(),
+            },
+            args: placeholders.expand_args(&self.args),
+            ..self.clone()
+        }
+    }
+
     /// Create a new [`std::process::Command`] from this command's configuration.
     pub fn as_std(&self) -> StdCommand {
         let mut ret = StdCommand::new(&self.program);
@@ -0,0 +1,113 @@
+//! Hashes of source file contents, used to skip reloads for files which haven't changed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use ignore::WalkBuilder;
+
+use crate::event_filter::FileEvent;
+use crate::haskell_source_file::is_haskell_source_file;
+use crate::normal_path::NormalPath;
+
+/// Hashes of the contents of Haskell source files.
+///
+/// Editors which save unchanged buffers, `touch`, and formatters which rewrite files identically
+/// all produce file events, but there's no need to reload for them.
+///
+/// Clones share the same hashes.
+#[derive(Debug, Clone, Default)]
+pub struct ContentHashes {
+    hashes: Arc<Mutex<HashMap<Utf8PathBuf, u64>>>,
+}
+
+impl ContentHashes {
+    /// Hash the Haskell source files under the given paths.
+    pub fn from_paths(paths: &[NormalPath]) -> Self {
+        let ret = Self::default();
+        ret.hash_paths(paths.iter().map(NormalPath::absolute));
+        ret
+    }
+
+    /// Hash the Haskell source files under the given paths, like newly-watched directories.
+    ///
+    /// This skips hidden and ignored files, which will be hashed when they first change instead.
+    pub fn hash_paths<'a>(&self, paths: impl IntoIterator<Item = &'a Utf8Path>) {
+        let mut hashes = self.lock();
+        let mut files = 0;
+        for path in paths {
+            for entry in WalkBuilder::new(path)
+                .build()
+                .filter_map(|entry| entry.ok())
+            {
+                let Ok(path) = Utf8PathBuf::try_from(entry.into_path()) else {
+                    continue;
+                };
+                if is_haskell_source_file(&path) {
+                    if let Some(hash) = read_hash(&path) {
+                        hashes.insert(path, hash);
+                        files += 1;
+                    }
+                }
+            }
+        }
+        tracing::debug!(files, "Hashed source files");
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Utf8PathBuf, u64>> {
+        self.hashes.lock().expect("Content hashes lock is poisoned")
+    }
+
+    /// Drop `Modify` events for Haskell source files whose contents haven't changed since they
+    /// were last hashed.
+    pub fn remove_unchanged(&self, events: BTreeSet<FileEvent>) -> BTreeSet<FileEvent> {
+        let mut hashes = self.lock();
+        events
+            .into_iter()
+            .filter(|event| match event {
+                FileEvent::Modify(path) => {
+                    if !is_haskell_source_file(path) {
+                        return true;
+                    }
+                    let Some(hash) = read_hash(path) else {
+                        return true;
+                    };
+                    if hashes.insert(path.clone(), hash) == Some(hash) {
+                        tracing::debug!(%path, "Skipping unchanged file");
+                        false
+                    } else {
+                        true
+                    }
+                }
+                FileEvent::Remove(path) => {
+                    hashes.remove(path);
+                    true
+                }
+            })
+            .collect()
+    }
+}
+
+/// Read a file and hash its contents.
+pub fn read_hash(path: &Utf8Path) -> Option<u64> {
+    match std::fs::read(path) {
+        Ok(contents) => Some(hash(&contents)),
+        Err(err) => {
+            tracing::debug!(%path, "Failed to read file: {err}");
+            None
+        }
+    }
+}
+
+/// Hash some file contents.
+pub fn hash(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
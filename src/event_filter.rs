@@ -4,12 +4,19 @@ use std::collections::BTreeSet;
 
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
+use ignore::WalkBuilder;
+use notify_debouncer_full::notify::event::ModifyKind;
 use notify_debouncer_full::notify::EventKind;
 use notify_debouncer_full::DebouncedEvent;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ignore::IgnoreFiles;
 
 /// A set of filesystem events that `ghci` will need to respond to. Due to the way that `ghci` is,
 /// we need to divide these into a few different classes so that we can respond appropriately.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum FileEvent {
     /// Existing files that are modified, or new files that are created.
     ///
@@ -33,13 +40,23 @@ impl FileEvent {
 }
 
 /// Process a set of events into a set of [`FileEvent`]s.
-pub fn file_events_from_action(events: Vec<DebouncedEvent>) -> eyre::Result<BTreeSet<FileEvent>> {
+///
+/// Files in newly-created directories are included, except for paths ignored by `ignore_files`.
+pub fn file_events_from_action(
+    events: Vec<DebouncedEvent>,
+    ignore_files: Option<&IgnoreFiles>,
+) -> eyre::Result<BTreeSet<FileEvent>> {
     let mut ret = BTreeSet::new();
 
     for event in events {
         let event = event.event;
         let mut modified = false;
         let mut removed = false;
+        // Directories which may have been created or moved into place.
+        let created = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+        );
         match event.kind {
             EventKind::Remove(_) => {
                 removed = true;
@@ -60,6 +77,16 @@ pub fn file_events_from_action(events: Vec<DebouncedEvent>) -> eyre::Result<BTre
             if !path.exists() || removed {
                 ret.insert(FileEvent::Remove(path));
             } else if modified {
+                if created && path.is_dir() {
+                    // On Linux, we often get an event for a new directory but none of the events
+                    // for the files inside of it (e.g. after `git checkout` or `cp -r`), so we
+                    // need to look for them ourselves.
+                    ret.extend(
+                        files_in_new_directory(&path, ignore_files)?
+                            .into_iter()
+                            .map(FileEvent::Modify),
+                    );
+                }
                 ret.insert(FileEvent::Modify(path));
             }
         }
@@ -67,3 +94,76 @@ pub fn file_events_from_action(events: Vec<DebouncedEvent>) -> eyre::Result<BTre
 
     Ok(ret)
 }
+
+/// Find all the files in a newly-created directory.
+///
+/// Paths ignored by `ignore_files` aren't walked, so that we don't list every file in a new
+/// `dist-newstyle` or `node_modules` directory.
+fn files_in_new_directory(
+    dir: &Utf8Path,
+    ignore_files: Option<&IgnoreFiles>,
+) -> eyre::Result<Vec<Utf8PathBuf>> {
+    let mut ret = Vec::new();
+    let mut walk = WalkBuilder::new(dir);
+    // `IgnoreFiles` reads the same ignore files as the `FileClassifier`, which the standard
+    // filters don't.
+    walk.standard_filters(false);
+    if let Some(ignore_files) = ignore_files {
+        let ignore_files = ignore_files.clone();
+        walk.filter_entry(move |entry| !ignore_files.is_ignored(entry.path()));
+    }
+    for entry in walk.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                // The directory may have been removed again already.
+                tracing::debug!(%dir, "Failed to walk new directory: {err}");
+                continue;
+            }
+        };
+
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            ret.push(entry.into_path().try_into()?);
+        }
+    }
+
+    if !ret.is_empty() {
+        tracing::debug!(%dir, files = ret.len(), "Found files in new directory");
+    }
+
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_files_in_new_directory() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::try_from(tempdir.path().to_owned()).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "dist-newstyle/\n").unwrap();
+        let dir = root.join("new");
+        std::fs::create_dir_all(dir.join("dist-newstyle")).unwrap();
+        std::fs::write(dir.join("MyLib.hs"), "").unwrap();
+        std::fs::write(dir.join("dist-newstyle/cache"), "").unwrap();
+
+        let mut files = files_in_new_directory(&dir, None).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [dir.join("MyLib.hs"), dir.join("dist-newstyle/cache")]
+        );
+
+        assert_eq!(
+            files_in_new_directory(&dir, Some(&IgnoreFiles::default())).unwrap(),
+            [dir.join("MyLib.hs")]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Recording file events to a file and replaying them later, to reproduce bugs.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use eyre::WrapErr;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tracing::instrument;
+
+use crate::event_filter::FileEvent;
+use crate::ghci::manager::WatcherEvent;
+use crate::normal_path::NormalPath;
+use crate::shutdown::ShutdownHandle;
+
+/// A batch of file events sent to the `ghci` session, as recorded in an events file.
+///
+/// Events files contain one JSON batch per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordedBatch {
+    /// When the batch was sent, in milliseconds since `ghci` first finished loading.
+    time_ms: u64,
+    /// The file events, with paths relative to the working directory when possible.
+    events: Vec<FileEvent>,
+}
+
+impl RecordedBatch {
+    fn new(time: Duration, events: &BTreeSet<FileEvent>) -> eyre::Result<Self> {
+        Ok(Self {
+            time_ms: time.as_millis().try_into()?,
+            events: events
+                .iter()
+                .map(|event| {
+                    map_event_path(
+                        event,
+                        |path| Ok(NormalPath::from_cwd(path)?.into_relative()),
+                    )
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn into_events(self) -> eyre::Result<BTreeSet<FileEvent>> {
+        self.events
+            .iter()
+            .map(|event| {
+                map_event_path(
+                    event,
+                    |path| Ok(NormalPath::from_cwd(path)?.into_absolute()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Transform the path in a [`FileEvent`].
+fn map_event_path(
+    event: &FileEvent,
+    f: impl FnOnce(&Utf8PathBuf) -> eyre::Result<Utf8PathBuf>,
+) -> eyre::Result<FileEvent> {
+    Ok(match event {
+        FileEvent::Modify(path) => FileEvent::Modify(f(path)?),
+        FileEvent::Remove(path) => FileEvent::Remove(f(path)?),
+    })
+}
+
+/// Write every batch of file events sent to the `ghci` session to a file, and forward them to
+/// the `ghci` session.
+///
+/// Events for the events file itself are dropped, so that recording them doesn't cause more
+/// events.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_event_recorder(
+    mut handle: ShutdownHandle,
+    mut receiver: mpsc::Receiver<WatcherEvent>,
+    ghci_sender: mpsc::Sender<WatcherEvent>,
+    mut ready: watch::Receiver<bool>,
+    path: Utf8PathBuf,
+) -> eyre::Result<()> {
+    let absolute_path = NormalPath::from_cwd(&path)?.into_absolute();
+    let mut file = tokio::fs::File::create(&path)
+        .await
+        .wrap_err_with(|| format!("Failed to create {path}"))?;
+    tracing::info!(%path, "Recording file events");
+
+    // Batches sent before `ghci` first finishes loading are recorded at time 0.
+    let mut start = None;
+
+    loop {
+        let mut event = tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break;
+            }
+            _ = wait_until_ready(&mut ready), if start.is_none() => {
+                start = Some(Instant::now());
+                continue;
+            }
+            Some(event) = receiver.recv() => event,
+        };
+
+        match &mut event {
+            WatcherEvent::Reload { events } => {
+                events.retain(|event| event.as_path() != absolute_path);
+                if events.is_empty() {
+                    continue;
+                }
+                let time = start.map(|start| start.elapsed()).unwrap_or_default();
+                let batch = RecordedBatch::new(time, events)?;
+                let mut line = serde_json::to_string(&batch)?;
+                line.push('\n');
+                file.write_all(line.as_bytes())
+                    .await
+                    .wrap_err_with(|| format!("Failed to write to {path}"))?;
+                file.flush().await?;
+            }
+        }
+
+        ghci_sender.send(event).await?;
+    }
+
+    Ok(())
+}
+
+/// Send the file events recorded in a file to the `ghci` session, with the same timing as they
+/// were recorded, starting once `ghci` first finishes loading.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_event_replay(
+    mut handle: ShutdownHandle,
+    ghci_sender: mpsc::Sender<WatcherEvent>,
+    mut ready: watch::Receiver<bool>,
+    path: Utf8PathBuf,
+) -> eyre::Result<()> {
+    let batches = read_events_file(&path).await?;
+
+    tokio::select! {
+        _ = handle.on_shutdown_requested() => {
+            return Ok(());
+        }
+        _ = wait_until_ready(&mut ready) => {}
+    }
+    let start = Instant::now();
+    tracing::info!(%path, "Replaying {} batches of file events", batches.len());
+
+    for batch in batches {
+        let time = start + Duration::from_millis(batch.time_ms);
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                return Ok(());
+            }
+            _ = tokio::time::sleep_until(time) => {}
+        }
+
+        let events = batch.into_events()?;
+        tracing::debug!(?events, "Replaying events");
+        ghci_sender.send(WatcherEvent::Reload { events }).await?;
+    }
+
+    tracing::info!("Finished replaying file events");
+    let _ = handle.on_shutdown_requested().await;
+    Ok(())
+}
+
+/// Wait until `ghci` first finishes loading.
+async fn wait_until_ready(ready: &mut watch::Receiver<bool>) {
+    while !*ready.borrow_and_update() {
+        if ready.changed().await.is_err() {
+            // `ghci` is shutting down, so it won't be ready.
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+async fn read_events_file(path: &Utf8PathBuf) -> eyre::Result<Vec<RecordedBatch>> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .wrap_err_with(|| format!("Failed to read {path}"))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .wrap_err_with(|| format!("Failed to parse {path}:{}", index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_recorded_batch() {
+        let cwd = crate::current_dir_utf8().unwrap();
+        let events = [
+            FileEvent::Modify(cwd.join("src/MyLib.hs")),
+            FileEvent::Remove(cwd.join("src/MyModule.hs")),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+
+        let batch = RecordedBatch::new(Duration::from_millis(1500), &events).unwrap();
+        let json = serde_json::to_string(&batch).unwrap();
+        assert_eq!(
+            json,
+            r#"{"time_ms":1500,"events":[{"modify":"src/MyLib.hs"},{"remove":"src/MyModule.hs"}]}"#
+        );
+
+        let batch: RecordedBatch = serde_json::from_str(&json).unwrap();
+        assert_eq!(batch.into_events().unwrap(), events);
+    }
+}
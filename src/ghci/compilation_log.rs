@@ -5,6 +5,7 @@ use crate::ghci::parse::CompilationSummary;
 use crate::ghci::parse::GhcDiagnostic;
 use crate::ghci::parse::GhcMessage;
 use crate::ghci::parse::Severity;
+use crate::ghci::parse::TestResults;
 
 use super::parse::ModulesLoaded;
 
@@ -13,6 +14,10 @@ use super::parse::ModulesLoaded;
 pub struct CompilationLog {
     pub summary: Option<CompilationSummary>,
     pub diagnostics: Vec<GhcDiagnostic>,
+    /// Names of the modules compiled, in order.
+    pub compiled_modules: Vec<String>,
+    /// Results parsed from test runner output, in order.
+    pub test_results: Vec<TestResults>,
 }
 
 impl CompilationLog {
@@ -50,6 +55,16 @@ impl CompilationLog {
         });
     }
 
+    /// Mark compilation as failed, like when a hook times out, so that the error log reports a
+    /// failure instead of "All good".
+    ///
+    /// This doesn't change which hooks run. If there's no compilation summary, this does nothing.
+    pub fn mark_failed(&mut self) {
+        if let Some(summary) = &mut self.summary {
+            summary.result = CompilationResult::Err;
+        }
+    }
+
     /// Get the result of compilation.
     pub fn result(&self) -> Option<CompilationResult> {
         self.summary.map(|summary| summary.result)
@@ -69,6 +84,7 @@ impl Extend<GhcMessage> for CompilationLog {
                         reason = progress.reason.as_deref().unwrap_or(""),
                         "Compiling",
                     );
+                    self.compiled_modules.push(progress.module.name);
                 }
                 GhcMessage::Diagnostic(diagnostic) => {
                     if let GhcDiagnostic {
@@ -0,0 +1,129 @@
+//! Files which modules depend on at compile time, like files embedded with `embedFile`.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+
+use crate::normal_path::NormalPath;
+
+use super::parse::parse_dependent_files;
+use super::parse::DependentFileBase;
+
+/// Files which modules depend on at compile time, and the modules which depend on them.
+///
+/// This is shared with the [`super::FileClassifier`]s, so that changes to these files reload the
+/// modules which depend on them.
+#[derive(Debug, Clone, Default)]
+pub struct DependentFiles {
+    /// For each module, the files it depends on, and whether GHC tracks each file.
+    modules: Arc<Mutex<BTreeMap<NormalPath, BTreeMap<Utf8PathBuf, bool>>>>,
+}
+
+impl DependentFiles {
+    /// Forget all modules and their dependent files.
+    pub fn clear(&self) {
+        self.modules
+            .lock()
+            .expect("Dependent files lock is poisoned")
+            .clear();
+    }
+
+    /// Find the dependent files of the module at `path` with the given contents.
+    pub fn update(&self, path: &NormalPath, contents: &str, cwd: &Utf8Path) {
+        let mut files = BTreeMap::new();
+        for file in parse_dependent_files(contents) {
+            let base = match file.base {
+                DependentFileBase::WorkingDirectory => cwd.to_owned(),
+                DependentFileBase::Project => project_dir(path.absolute(), cwd),
+                DependentFileBase::Module => path
+                    .absolute()
+                    .parent()
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_else(|| cwd.to_owned()),
+            };
+            let absolute = NormalPath::new(&file.path, &base)
+                .map(NormalPath::into_absolute)
+                .unwrap_or_else(|_| base.join(&file.path));
+            // If any use of the file tracks it, GHC will notice when it changes.
+            *files.entry(absolute).or_insert(false) |= file.tracked;
+        }
+
+        let mut modules = self
+            .modules
+            .lock()
+            .expect("Dependent files lock is poisoned");
+        if files.is_empty() {
+            modules.remove(path);
+        } else {
+            tracing::debug!(%path, ?files, "Found dependent files");
+            modules.insert(path.clone(), files);
+        }
+    }
+
+    /// Forget the dependent files of the module at `path`.
+    pub fn remove(&self, path: &NormalPath) {
+        self.modules
+            .lock()
+            .expect("Dependent files lock is poisoned")
+            .remove(path);
+    }
+
+    /// Get the modules which depend on the file at `path`, or on a directory containing it.
+    pub fn modules_depending_on(&self, path: &NormalPath) -> Vec<NormalPath> {
+        self.modules
+            .lock()
+            .expect("Dependent files lock is poisoned")
+            .iter()
+            .filter(|(_, files)| files.keys().any(|file| path.absolute().starts_with(file)))
+            .map(|(module, _)| module.clone())
+            .collect()
+    }
+
+    /// Does a module depend on the file at `path` (or a directory containing it) without GHC
+    /// tracking it? `:reload` won't recompile the module for changes to such a file, so it needs
+    /// to be forced.
+    pub fn is_untracked(&self, path: &NormalPath) -> bool {
+        self.modules
+            .lock()
+            .expect("Dependent files lock is poisoned")
+            .values()
+            .flatten()
+            .any(|(file, tracked)| !tracked && path.absolute().starts_with(file))
+    }
+
+    /// Get all the files which modules depend on.
+    pub fn files(&self) -> BTreeSet<Utf8PathBuf> {
+        self.modules
+            .lock()
+            .expect("Dependent files lock is poisoned")
+            .values()
+            .flat_map(|files| files.keys())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Find the root directory of the package containing `path`: the closest directory with a
+/// `.cabal` or `package.yaml` file, or the working directory.
+fn project_dir(path: &Utf8Path, cwd: &Utf8Path) -> Utf8PathBuf {
+    for dir in path.ancestors().skip(1) {
+        let is_project_dir = dir.read_dir_utf8().is_ok_and(|mut entries| {
+            entries.any(|entry| {
+                entry.is_ok_and(|entry| {
+                    entry.path().extension() == Some("cabal") || entry.file_name() == "package.yaml"
+                })
+            })
+        });
+        if is_project_dir {
+            return dir.to_owned();
+        }
+        if dir == cwd {
+            break;
+        }
+    }
+    cwd.to_owned()
+}
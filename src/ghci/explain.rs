@@ -0,0 +1,55 @@
+//! The `ghciwatch explain` subcommand.
+
+use eyre::WrapErr;
+use tokio::sync::mpsc;
+use tracing::instrument;
+
+use crate::normal_path::NormalPath;
+use crate::shutdown::ShutdownHandle;
+
+use super::CompilationLog;
+use super::Ghci;
+use super::GhciOpts;
+use super::GhciWriter;
+
+/// Start a `ghci` session, print how changes to the given paths would be handled, and then shut
+/// down.
+#[instrument(skip_all, level = "debug")]
+pub async fn run_explain(
+    mut handle: ShutdownHandle,
+    opts: GhciOpts,
+    paths: Vec<NormalPath>,
+) -> eyre::Result<()> {
+    // Start and load the session without running any hooks or writing the error log, so we
+    // don't interfere with a `ghciwatch` session which may be running. `ghci`'s output goes to
+    // stderr, so that stdout only contains the explanations.
+    let opts = GhciOpts {
+        hooks: Default::default(),
+        stdout_writer: GhciWriter::stderr(),
+        ..opts
+    };
+    let (exited_sender, _exited_receiver) = mpsc::channel(1);
+    let mut ghci = Ghci::new(handle.clone(), opts, exited_sender)
+        .await
+        .wrap_err("Failed to start `ghci`")?;
+
+    let mut log = CompilationLog::default();
+    tokio::select! {
+        _ = handle.on_shutdown_requested() => {
+            ghci.stop().await.wrap_err("Failed to quit ghci")?;
+            return Ok(());
+        }
+        result = ghci.initialize_inner(&mut log) => result?,
+    }
+
+    for path in &paths {
+        let (path, classification) = ghci.explain(path)?;
+        let explanation = format!("{path}: {classification}");
+        tracing::debug!(%explanation, "Explained path");
+        println!("{explanation}");
+    }
+
+    ghci.stop().await.wrap_err("Failed to quit ghci")?;
+    let _ = handle.request_shutdown();
+    Ok(())
+}
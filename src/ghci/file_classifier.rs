@@ -1,17 +1,23 @@
 //! Classifies file events into reload actions based on glob patterns.
 
 use std::collections::BTreeSet;
+use std::fmt::Display;
 use std::path::Path;
 
 use camino::Utf8PathBuf;
+use itertools::Itertools;
 
 use crate::event_filter::FileEvent;
 use crate::haskell_source_file::is_haskell_source_file;
+use crate::haskell_source_file::HASKELL_SOURCE_EXTENSIONS;
 use crate::ignore::GlobMatcher;
+use crate::ignore::IgnoreFiles;
 use crate::normal_path::NormalPath;
 
 use super::module_set::ModuleSet;
+use super::DependentFiles;
 use super::GhciReloadKind;
+use super::WrittenFiles;
 
 /// Classifies file events into reload actions based on glob patterns
 /// and the current state of loaded modules.
@@ -24,8 +30,14 @@ pub struct FileClassifier {
     restart_globs: GlobMatcher,
     /// Reload the `ghci` session when paths matching these globs are changed.
     reload_globs: GlobMatcher,
+    /// Ignore changes to paths ignored by `.gitignore` and similar files.
+    ignore_files: Option<IgnoreFiles>,
     /// The working directory used to make paths relative.
     cwd: Utf8PathBuf,
+    /// Files written by `ghciwatch`, which don't need to be reloaded until they're changed again.
+    written_files: WrittenFiles,
+    /// Files which modules depend on at compile time.
+    dependent_files: DependentFiles,
 }
 
 impl FileClassifier {
@@ -33,11 +45,20 @@ impl FileClassifier {
     /// current working directory.
     ///
     /// This is suitable for use before GHCi has initialized.
-    pub fn new(restart_globs: GlobMatcher, reload_globs: GlobMatcher) -> eyre::Result<Self> {
+    pub fn new(
+        restart_globs: GlobMatcher,
+        reload_globs: GlobMatcher,
+        ignore_files: Option<IgnoreFiles>,
+        written_files: WrittenFiles,
+        dependent_files: DependentFiles,
+    ) -> eyre::Result<Self> {
         Ok(Self {
             restart_globs,
             reload_globs,
+            ignore_files,
             cwd: crate::current_dir_utf8()?,
+            written_files,
+            dependent_files,
         })
     }
 
@@ -68,68 +89,39 @@ impl FileClassifier {
         let mut needs_reload = Vec::new();
         let mut needs_add = Vec::new();
         let mut needs_remove = Vec::new();
+        let mut needs_force_recompile = false;
         for event in events {
-            let path = event.as_path();
-            let path = self.relative_path(path)?;
-
-            let restart_match = self.restart_globs.matched(&path);
-            let reload_match = self.reload_globs.matched(&path);
-            let path_is_haskell_source_file = is_haskell_source_file(&path);
-            tracing::trace!(
-                ?event,
-                ?restart_match,
-                ?reload_match,
-                is_haskell_source_file = path_is_haskell_source_file,
-                "Checking path"
-            );
-
-            // Don't restart if we've explicitly ignored this path in a glob.
-            if !restart_match.is_ignore()
-                // Restart on `.cabal` and `.ghci` files.
-                && (path
-                    .extension()
-                    .map(|ext| ext == "cabal")
-                    .unwrap_or(false)
-                || path
-                    .file_name()
-                    .map(|name| name == ".ghci")
-                    .unwrap_or(false)
-                // Restart on explicit restart globs.
-                || restart_match.is_whitelist())
-            {
-                // Restart for this path.
-                tracing::debug!(%path, "Needs restart");
-                needs_restart.push(path);
-            } else if reload_match.is_ignore() {
-                // Ignoring this path, continue.
-            } else if matches!(event, FileEvent::Remove(_))
-                && path_is_haskell_source_file
-                && targets.contains_source_path(&path)
-            {
-                tracing::debug!(%path, "Needs remove");
-                needs_remove.push(path);
-            } else if matches!(event, FileEvent::Modify(_))
-                && path_is_haskell_source_file
-                // Unless a user explicitly asks for it (e.g. `--reload-glob '**/.*.hs`), ignore
-                // dotfiles when reloading.
-                && !path
-                    .file_name()
-                    .is_some_and(|name| name.starts_with('.'))
-            {
-                // Otherwise, reload when Haskell files are modified.
-                if targets.contains_source_path(&path) {
-                    // We can `:reload` paths in the target set.
-                    tracing::debug!(%path, "Needs reload");
-                    needs_reload.push(path);
-                } else {
-                    // Otherwise we need to `:add` the new paths.
-                    tracing::debug!(%path, "Needs add");
-                    needs_add.push(path);
+            let path = self.relative_path(event.as_path())?;
+            let Classification {
+                action,
+                reason,
+                dependent_modules,
+                force_recompile,
+            } = self.classify_path(&event, &path, targets);
+            needs_force_recompile |= force_recompile;
+            match (action, reason) {
+                (FileAction::Restart, _) => needs_restart.push(path),
+                // The path itself isn't a module; only its dependent modules are reloaded.
+                (FileAction::Reload, ClassifyReason::DependentFile(_)) => {}
+                (FileAction::Reload, _) => needs_reload.push(path),
+                (FileAction::Add, _) => {
+                    if !needs_add.contains(&path) {
+                        needs_add.push(path);
+                    }
+                }
+                (FileAction::Remove, _) => needs_remove.push(path),
+                (FileAction::Ignore, _) => {}
+            }
+            for module in dependent_modules {
+                if targets.contains_source_path(&module) {
+                    if !needs_reload.contains(&module) {
+                        needs_reload.push(module);
+                    }
+                } else if !needs_add.contains(&module) {
+                    // `:reload` only recompiles loaded modules.
+                    tracing::debug!(%module, "Needs add for dependent file, because it isn't loaded");
+                    needs_add.push(module);
                 }
-            } else if reload_match.is_whitelist() {
-                // Extra extensions are always reloaded, never added.
-                tracing::debug!(%path, "Needs reload");
-                needs_reload.push(path);
             }
         }
 
@@ -138,8 +130,288 @@ impl FileClassifier {
             needs_reload,
             needs_add,
             needs_remove,
+            needs_force_recompile,
         })
     }
+
+    /// Classify a single file event, and explain which rule produced the classification.
+    ///
+    /// `path` is the event's path, relative to the working directory.
+    pub(crate) fn classify_path(
+        &self,
+        event: &FileEvent,
+        path: &NormalPath,
+        targets: &ModuleSet,
+    ) -> Classification {
+        if matches!(event, FileEvent::Modify(_)) && self.written_files.is_unchanged(path) {
+            tracing::debug!(%path, "Ignoring change written by ghciwatch");
+            return Classification::new(FileAction::Ignore, ClassifyReason::WrittenByGhciwatch);
+        }
+
+        let mut classification = self.classify_path_by_rules(event, path, targets);
+
+        // Reload modules which depend on this path at compile time, in addition to whatever the
+        // path itself needs. GHC tracks files added with `addDependentFile`, so `:reload` will
+        // recompile the modules, but other files (like those read with `runIO`) need the
+        // recompile to be forced.
+        let dependent_modules = self.dependent_files.modules_depending_on(path);
+        if !dependent_modules.is_empty() {
+            for module in &dependent_modules {
+                tracing::debug!(%path, %module, "Needs reload for dependent file");
+            }
+            if classification.action == FileAction::Ignore {
+                classification = Classification::new(
+                    FileAction::Reload,
+                    ClassifyReason::DependentFile(dependent_modules.clone()),
+                );
+            }
+            classification.dependent_modules = dependent_modules;
+            classification.force_recompile = self.dependent_files.is_untracked(path);
+        }
+
+        classification
+    }
+
+    /// Classify a single file event with the glob, ignore file, and Haskell source file rules.
+    fn classify_path_by_rules(
+        &self,
+        event: &FileEvent,
+        path: &NormalPath,
+        targets: &ModuleSet,
+    ) -> Classification {
+        let restart_match = self.restart_globs.matched(path);
+        let reload_match = self.reload_globs.matched(path);
+        let path_is_haskell_source_file = is_haskell_source_file(path);
+        tracing::trace!(
+            ?event,
+            ?restart_match,
+            ?reload_match,
+            is_haskell_source_file = path_is_haskell_source_file,
+            "Checking path"
+        );
+
+        let is_cabal_file = path.extension().map(|ext| ext == "cabal").unwrap_or(false);
+        let is_dot_ghci = path
+            .file_name()
+            .map(|name| name == ".ghci")
+            .unwrap_or(false);
+        let is_dotfile = path.file_name().is_some_and(|name| name.starts_with('.'));
+
+        // Explicit globs take precedence over ignore files. `.cabal` and `.ghci` files are never
+        // ignored, because `.cabal` files generated by `hpack` are often gitignored.
+        if !restart_match.is_whitelist()
+            && !reload_match.is_whitelist()
+            && !is_cabal_file
+            && !is_dot_ghci
+            && self
+                .ignore_files
+                .as_ref()
+                .is_some_and(|ignore_files| ignore_files.is_ignored(path.absolute().as_std_path()))
+        {
+            tracing::debug!(%path, "Ignoring change to ignored path");
+            return Classification::new(FileAction::Ignore, ClassifyReason::IgnoreFile);
+        }
+
+        // Don't restart if we've explicitly ignored this path in a glob.
+        if !restart_match.is_ignore()
+            // Restart on `.cabal` and `.ghci` files.
+            && (is_cabal_file
+            || is_dot_ghci
+            // Restart on explicit restart globs.
+            || restart_match.is_whitelist())
+        {
+            // Restart for this path.
+            tracing::debug!(%path, "Needs restart");
+            let reason = match restart_match.inner() {
+                Some(glob) if restart_match.is_whitelist() => {
+                    ClassifyReason::RestartGlob(glob.original().to_owned())
+                }
+                _ if is_cabal_file => ClassifyReason::CabalFile,
+                _ => ClassifyReason::GhciFile,
+            };
+            Classification::new(FileAction::Restart, reason)
+        } else if let (true, Some(glob)) = (reload_match.is_ignore(), reload_match.inner()) {
+            // Ignoring this path, continue.
+            Classification::new(
+                FileAction::Ignore,
+                ClassifyReason::ReloadGlob(glob.original().to_owned()),
+            )
+        } else if matches!(event, FileEvent::Remove(_))
+            && path_is_haskell_source_file
+            && targets.contains_source_path(path)
+        {
+            tracing::debug!(%path, "Needs remove");
+            Classification::new(FileAction::Remove, ClassifyReason::HaskellSourceFile)
+        } else if matches!(event, FileEvent::Modify(_))
+            && path_is_haskell_source_file
+            // Unless a user explicitly asks for it (e.g. `--reload-glob '**/.*.hs`), ignore
+            // dotfiles when reloading.
+            && !is_dotfile
+        {
+            // Otherwise, reload when Haskell files are modified.
+            if targets.contains_source_path(path) {
+                // We can `:reload` paths in the target set.
+                tracing::debug!(%path, "Needs reload");
+                Classification::new(FileAction::Reload, ClassifyReason::HaskellSourceFile)
+            } else {
+                // Otherwise we need to `:add` the new paths.
+                tracing::debug!(%path, "Needs add");
+                Classification::new(FileAction::Add, ClassifyReason::HaskellSourceFile)
+            }
+        } else if let (true, Some(glob)) = (reload_match.is_whitelist(), reload_match.inner()) {
+            // Extra extensions are always reloaded, never added.
+            tracing::debug!(%path, "Needs reload");
+            Classification::new(
+                FileAction::Reload,
+                ClassifyReason::ReloadGlob(glob.original().to_owned()),
+            )
+        } else if is_dotfile
+            && path
+                .extension()
+                .is_some_and(|ext| HASKELL_SOURCE_EXTENSIONS.contains(&ext))
+        {
+            Classification::new(FileAction::Ignore, ClassifyReason::Dotfile)
+        } else if path_is_haskell_source_file {
+            // A removed Haskell source file which isn't loaded.
+            Classification::new(FileAction::Ignore, ClassifyReason::NotLoaded)
+        } else if let (true, Some(glob)) = (restart_match.is_ignore(), restart_match.inner()) {
+            // Explicitly excluded from the restart globs, and nothing else matches.
+            Classification::new(
+                FileAction::Ignore,
+                ClassifyReason::RestartGlob(glob.original().to_owned()),
+            )
+        } else {
+            Classification::new(FileAction::Ignore, ClassifyReason::NoMatch)
+        }
+    }
+}
+
+/// How a file event is classified, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Classification {
+    /// What to do in response to the event.
+    pub action: FileAction,
+    /// The rule which decided the action.
+    pub reason: ClassifyReason,
+    /// Modules which depend on the path at compile time, which are reloaded as well.
+    pub dependent_modules: Vec<NormalPath>,
+    /// GHC doesn't track the path as a dependency of `dependent_modules`, so recompiling them
+    /// needs to be forced.
+    pub force_recompile: bool,
+}
+
+impl Classification {
+    fn new(action: FileAction, reason: ClassifyReason) -> Self {
+        Self {
+            action,
+            reason,
+            dependent_modules: Vec::new(),
+            force_recompile: false,
+        }
+    }
+}
+
+impl Display for Classification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.action, self.reason)?;
+        if !self.dependent_modules.is_empty()
+            && !matches!(self.reason, ClassifyReason::DependentFile(_))
+        {
+            write!(
+                f,
+                ", and reload {} (TemplateHaskell dependency)",
+                self.dependent_modules.iter().join(", ")
+            )?;
+        }
+        if self.force_recompile {
+            write!(f, ", forcing recompilation")?;
+        }
+        Ok(())
+    }
+}
+
+/// What to do in response to a file event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileAction {
+    /// Restart the `ghci` session.
+    Restart,
+    /// `:reload` the `ghci` session.
+    Reload,
+    /// `:add` the module.
+    Add,
+    /// `:unadd` the module.
+    Remove,
+    /// Do nothing.
+    Ignore,
+}
+
+impl Display for FileAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileAction::Restart => write!(f, "restart"),
+            FileAction::Reload => write!(f, "reload"),
+            FileAction::Add => write!(f, "add"),
+            FileAction::Remove => write!(f, "remove"),
+            FileAction::Ignore => write!(f, "ignored"),
+        }
+    }
+}
+
+/// The rule which decided a [`FileAction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ClassifyReason {
+    /// The file was written by `ghciwatch` and hasn't changed since.
+    WrittenByGhciwatch,
+    /// These modules depend on the file at compile time.
+    DependentFile(Vec<NormalPath>),
+    /// The path is ignored by a `.gitignore` or similar file.
+    IgnoreFile,
+    /// The path matches this `--restart-glob`.
+    RestartGlob(String),
+    /// The path matches this `--reload-glob`.
+    ReloadGlob(String),
+    /// The path is a `.cabal` file.
+    CabalFile,
+    /// The path is a `.ghci` file.
+    GhciFile,
+    /// The path is a Haskell source file.
+    HaskellSourceFile,
+    /// The path is a Haskell source file whose name starts with `.`.
+    Dotfile,
+    /// The path is a removed Haskell source file which isn't loaded.
+    NotLoaded,
+    /// No rule matches the path.
+    NoMatch,
+}
+
+impl Display for ClassifyReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClassifyReason::WrittenByGhciwatch => {
+                write!(f, "written by ghciwatch and unchanged since")
+            }
+            ClassifyReason::DependentFile(modules) => {
+                write!(
+                    f,
+                    "TemplateHaskell dependency of {}",
+                    modules.iter().join(", ")
+                )
+            }
+            ClassifyReason::IgnoreFile => write!(f, "ignored by an ignore file"),
+            ClassifyReason::RestartGlob(glob) => write!(f, "matches `--restart-glob {glob}`"),
+            ClassifyReason::ReloadGlob(glob) => write!(f, "matches `--reload-glob {glob}`"),
+            ClassifyReason::CabalFile => write!(f, "`.cabal` file"),
+            ClassifyReason::GhciFile => write!(f, "`.ghci` file"),
+            ClassifyReason::HaskellSourceFile => write!(f, "Haskell source file"),
+            ClassifyReason::Dotfile => write!(f, "Haskell source file starting with `.`"),
+            ClassifyReason::NotLoaded => {
+                write!(f, "removed Haskell source file which isn't loaded")
+            }
+            ClassifyReason::NoMatch => {
+                write!(f, "not a Haskell source file and no globs match")
+            }
+        }
+    }
 }
 
 /// Actions needed to perform a reload.
@@ -155,6 +427,8 @@ pub(crate) struct ReloadActions {
     pub needs_add: Vec<NormalPath>,
     /// Paths to modules which need an `:unadd`.
     pub needs_remove: Vec<NormalPath>,
+    /// Do modules need to be recompiled for changes to files GHC doesn't track?
+    pub needs_force_recompile: bool,
 }
 
 impl ReloadActions {
@@ -168,6 +442,19 @@ impl ReloadActions {
         !self.needs_restart.is_empty()
     }
 
+    /// Get all the paths changed by the file events these actions were classified from.
+    pub fn changed_paths(&self) -> Vec<NormalPath> {
+        self.needs_restart
+            .iter()
+            .chain(&self.needs_reload)
+            .chain(&self.needs_add)
+            .chain(&self.needs_remove)
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
     /// Get the kind of reload we'll perform.
     pub fn kind(&self) -> GhciReloadKind {
         if self.needs_restart() {
@@ -179,3 +466,267 @@ impl ReloadActions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::loaded_module::LoadedModule;
+
+    #[test]
+    fn test_classify_path() {
+        let classifier = FileClassifier::new(
+            GlobMatcher::from_globs(["config/**", "!config/local.yaml"]).unwrap(),
+            GlobMatcher::from_globs(["**/*.persistentmodels", "!src/Generated/**"]).unwrap(),
+            None,
+            WrittenFiles::default(),
+            DependentFiles::default(),
+        )
+        .unwrap();
+        let mut targets = ModuleSet::default();
+        targets.insert_module(LoadedModule::new(
+            classifier.relative_path("src/MyLib.hs").unwrap(),
+        ));
+
+        let classify = |event: fn(Utf8PathBuf) -> FileEvent, path: &str| {
+            let path = classifier.relative_path(path).unwrap();
+            let event = event(path.absolute().to_owned());
+            classifier
+                .classify_path(&event, &path, &targets)
+                .to_string()
+        };
+
+        assert_eq!(
+            classify(FileEvent::Modify, "src/MyLib.hs"),
+            "reload (Haskell source file)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "src/MyModule.hs"),
+            "add (Haskell source file)"
+        );
+        assert_eq!(
+            classify(FileEvent::Remove, "src/MyLib.hs"),
+            "remove (Haskell source file)"
+        );
+        assert_eq!(
+            classify(FileEvent::Remove, "src/MyModule.hs"),
+            "ignored (removed Haskell source file which isn't loaded)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "src/.MyLib.hs"),
+            "ignored (Haskell source file starting with `.`)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "my-package.cabal"),
+            "restart (`.cabal` file)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, ".ghci"),
+            "restart (`.ghci` file)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "config/settings.yaml"),
+            "restart (matches `--restart-glob config/**`)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "config/local.yaml"),
+            "ignored (matches `--restart-glob !config/local.yaml`)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "models/user.persistentmodels"),
+            "reload (matches `--reload-glob **/*.persistentmodels`)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "src/Generated/Types.hs"),
+            "ignored (matches `--reload-glob !src/Generated/**`)"
+        );
+        assert_eq!(
+            classify(FileEvent::Modify, "README.md"),
+            "ignored (not a Haskell source file and no globs match)"
+        );
+    }
+
+    #[test]
+    fn test_classify_path_ignore_files() {
+        let root = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::try_from(root.path().to_owned()).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.cabal\n.ghci\ndist-newstyle/\n").unwrap();
+
+        let mut classifier = FileClassifier::new(
+            GlobMatcher::empty(),
+            GlobMatcher::empty(),
+            Some(IgnoreFiles::default()),
+            WrittenFiles::default(),
+            DependentFiles::default(),
+        )
+        .unwrap();
+        classifier.set_cwd(root.clone());
+
+        let classify = |path: &str| {
+            let path = classifier.relative_path(root.join(path)).unwrap();
+            let event = FileEvent::Modify(path.absolute().to_owned());
+            classifier
+                .classify_path(&event, &path, &ModuleSet::default())
+                .to_string()
+        };
+
+        assert_eq!(
+            classify("dist-newstyle/src/MyLib.hs"),
+            "ignored (ignored by an ignore file)"
+        );
+        // Generated `.cabal` files are often gitignored, but they still restart the session.
+        assert_eq!(classify("my-package.cabal"), "restart (`.cabal` file)");
+        assert_eq!(classify(".ghci"), "restart (`.ghci` file)");
+    }
+
+    #[test]
+    fn test_classify_dependent_files() {
+        let dependent_files = DependentFiles::default();
+        let classifier = FileClassifier::new(
+            GlobMatcher::from_globs(["config/**"]).unwrap(),
+            GlobMatcher::empty(),
+            None,
+            WrittenFiles::default(),
+            dependent_files.clone(),
+        )
+        .unwrap();
+        let module = classifier.relative_path("src/Embedded.hs").unwrap();
+        dependent_files.update(
+            &module,
+            "addDependentFile \"data/message.txt\"\naddDependentFile \"config/settings.yaml\"\n",
+            &classifier.cwd,
+        );
+
+        let classify = |path: &str| {
+            let path = classifier.relative_path(path).unwrap();
+            let event = FileEvent::Modify(path.absolute().to_owned());
+            classifier
+                .classify_path(&event, &path, &ModuleSet::default())
+                .to_string()
+        };
+
+        assert_eq!(
+            classify("data/message.txt"),
+            "reload (TemplateHaskell dependency of src/Embedded.hs)"
+        );
+        assert_eq!(
+            classify("config/settings.yaml"),
+            "restart (matches `--restart-glob config/**`), \
+            and reload src/Embedded.hs (TemplateHaskell dependency)"
+        );
+
+        let events = [
+            FileEvent::Modify(
+                classifier
+                    .relative_path("data/message.txt")
+                    .unwrap()
+                    .into_absolute(),
+            ),
+            FileEvent::Modify(
+                classifier
+                    .relative_path("config/settings.yaml")
+                    .unwrap()
+                    .into_absolute(),
+            ),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+
+        let mut targets = ModuleSet::default();
+        targets.insert_module(LoadedModule::new(module.clone()));
+        let actions = classifier.classify(events.clone(), &targets).unwrap();
+        assert_eq!(
+            actions.needs_restart,
+            vec![classifier.relative_path("config/settings.yaml").unwrap()]
+        );
+        assert_eq!(actions.needs_reload, vec![module.clone()]);
+        assert_eq!(actions.needs_add, vec![]);
+        assert!(!actions.needs_force_recompile);
+    }
+
+    /// Test that changes to files which GHC doesn't track as dependencies force recompilation,
+    /// because `:reload` would consider the modules which depend on them up to date.
+    #[test]
+    fn test_classify_untracked_dependent_files() {
+        let dependent_files = DependentFiles::default();
+        let classifier = FileClassifier::new(
+            GlobMatcher::empty(),
+            GlobMatcher::empty(),
+            None,
+            WrittenFiles::default(),
+            dependent_files.clone(),
+        )
+        .unwrap();
+        let module = classifier.relative_path("src/Embedded.hs").unwrap();
+        dependent_files.update(
+            &module,
+            "message = $(runIO (readFile \"data/message.txt\") >>= lift)\n",
+            &classifier.cwd,
+        );
+
+        let path = classifier.relative_path("data/message.txt").unwrap();
+        let event = FileEvent::Modify(path.absolute().to_owned());
+        assert_eq!(
+            classifier
+                .classify_path(&event, &path, &ModuleSet::default())
+                .to_string(),
+            "reload (TemplateHaskell dependency of src/Embedded.hs), forcing recompilation"
+        );
+
+        let mut targets = ModuleSet::default();
+        targets.insert_module(LoadedModule::new(module.clone()));
+        let actions = classifier
+            .classify([event].into_iter().collect(), &targets)
+            .unwrap();
+        assert_eq!(actions.needs_reload, vec![module]);
+        assert!(actions.needs_force_recompile);
+    }
+
+    /// Test that modules which depend on a changed file but aren't loaded are added, because
+    /// `:reload` wouldn't recompile them.
+    #[test]
+    fn test_classify_dependent_files_not_loaded() {
+        let dependent_files = DependentFiles::default();
+        let classifier = FileClassifier::new(
+            GlobMatcher::empty(),
+            GlobMatcher::empty(),
+            None,
+            WrittenFiles::default(),
+            dependent_files.clone(),
+        )
+        .unwrap();
+        let module = classifier.relative_path("src/Embedded.hs").unwrap();
+        let loaded_module = classifier.relative_path("src/MyLib.hs").unwrap();
+        dependent_files.update(
+            &module,
+            "addDependentFile \"data/message.txt\"\n",
+            &classifier.cwd,
+        );
+        dependent_files.update(
+            &loaded_module,
+            "addDependentFile \"data/message.txt\"\n",
+            &classifier.cwd,
+        );
+
+        let mut targets = ModuleSet::default();
+        targets.insert_module(LoadedModule::new(loaded_module.clone()));
+        let actions = classifier
+            .classify(
+                [FileEvent::Modify(
+                    classifier
+                        .relative_path("data/message.txt")
+                        .unwrap()
+                        .into_absolute(),
+                )]
+                .into_iter()
+                .collect(),
+                &targets,
+            )
+            .unwrap();
+        assert_eq!(actions.needs_reload, vec![loaded_module]);
+        assert_eq!(actions.needs_add, vec![module]);
+    }
+}
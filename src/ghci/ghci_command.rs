@@ -6,6 +6,8 @@ use clap::builder::StringValueParser;
 use clap::builder::TypedValueParser;
 use clap::builder::ValueParserFactory;
 
+use crate::placeholders::Placeholders;
+
 /// A `ghci` command.
 ///
 /// This is a string that can be written to a `ghci` session, typically a Haskell expression or
@@ -18,6 +20,12 @@ impl GhciCommand {
     pub fn into_string(self) -> String {
         self.0
     }
+
+    /// Expand [`Placeholders`] like `{changed_modules}` in this command, escaping their values
+    /// for use in Haskell string literals.
+    pub fn expand(&self, placeholders: &Placeholders) -> Self {
+        Self(placeholders.expand_haskell(&self.0))
+    }
 }
 
 impl Debug for GhciCommand {
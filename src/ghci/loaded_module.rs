@@ -6,6 +6,7 @@ use std::hash::Hasher;
 use camino::Utf8Path;
 
 use crate::normal_path::NormalPath;
+use crate::path_map::PathMap;
 
 /// Information about a module loaded into a `ghci` session.
 ///
@@ -45,6 +46,15 @@ impl LoadedModule {
     pub fn path(&self) -> &NormalPath {
         &self.path
     }
+
+    /// Format this module for a `ghci` command, like [`Display`], but using the path `ghci` sees
+    /// if the module's path is mapped.
+    pub fn for_ghci(&self, path_map: &PathMap) -> String {
+        match (&self.name, path_map.to_ghci(self.path.absolute())) {
+            (None, Some(path)) => path.into_string(),
+            _ => self.to_string(),
+        }
+    }
 }
 
 impl Display for LoadedModule {
@@ -7,7 +7,9 @@ use std::sync::Arc;
 use eyre::Context;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
+use tokio::sync::watch;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tracing::instrument;
 
 use crate::event_filter::FileEvent;
@@ -61,6 +63,8 @@ pub async fn run_ghci(
     // is a little different each time, so the `select!`s can't be consolidated.
 
     let interrupt_reloads = opts.interrupt_reloads;
+    let interrupt_tests = opts.interrupt_tests;
+    let ready = opts.ready.clone();
     let classifier = opts.file_classifier()?;
     let (exited_sender, mut exited_receiver) = mpsc::channel::<ExitStatus>(1);
     let mut ghci = Ghci::new(handle.clone(), opts, exited_sender)
@@ -114,6 +118,11 @@ pub async fn run_ghci(
         }
     }
 
+    if let Some(ready) = ready {
+        ready.send_replace(true);
+    }
+
+    let tests_running = ghci.subscribe_tests_running();
     let manager = GhciManager {
         ghci: Arc::new(Mutex::new(ghci)),
         handle,
@@ -121,6 +130,8 @@ pub async fn run_ghci(
         exited_receiver,
         classifier,
         interrupt_reloads,
+        interrupt_tests,
+        tests_running,
     };
     manager.run().await
 }
@@ -171,6 +182,9 @@ struct GhciManager {
     exited_receiver: mpsc::Receiver<ExitStatus>,
     classifier: FileClassifier,
     interrupt_reloads: bool,
+    interrupt_tests: bool,
+    /// Whether `ghci` is running the test hooks.
+    tests_running: watch::Receiver<bool>,
 }
 
 /// Result of [`GhciManager::wait_for_event`].
@@ -288,7 +302,8 @@ impl GhciManager {
                 ref mut exited_receiver,
                 ref classifier,
                 interrupt_reloads,
-                ..
+                interrupt_tests,
+                ref mut tests_running,
             } = *self;
             break tokio::select! {
                 _ = handle.on_shutdown_requested() => {
@@ -324,63 +339,61 @@ impl GhciManager {
                         continue;
                     }
 
-                    // Check if we should interrupt the in-progress reload. We can only
-                    // check once (the oneshot is consumed), and only for interruptible
-                    // reloads.
-                    if interrupt_reloads {
-                        if let Some(reload_receiver) = reload_receiver.take() {
-                            if should_interrupt(reload_receiver).await {
-                                // Merge everything: any previously accumulated events
-                                // plus the newest event.
-                                if let Some(pending_event) = pending_event.take() {
-                                    event.merge(pending_event);
-                                }
-                                event.merge(new_event);
-
-                                // Cancel the in-progress reload. This releases the
-                                // `ghci` lock to prevent a deadlock.
-                                task.abort();
-
-                                // Send a SIGINT to interrupt the reload.
-                                // NB: This may take a couple seconds to register.
-                                match ghci.lock().await.send_sigint().await {
-                                    Ok(()) => return Ok(HandleResult::Interrupted(event)),
-                                    Err(e) => {
-                                        // `send_sigint` may kill the session if it
-                                        // cannot leave ghci in a usable state (e.g.
-                                        // sync barrier failure). Wait for the exit
-                                        // and route through the standard restart
-                                        // path with the merged event preserved.
-                                        tracing::warn!(
-                                            error = ?e,
-                                            "Failed to interrupt ghci; session was killed for restart",
-                                        );
-                                        pending_event = Some(event);
-                                        let status = exited_receiver
-                                            .recv()
-                                            .await
-                                            .ok_or_else(|| {
-                                                eyre::eyre!(
-                                                    "ghci exit channel closed after kill"
-                                                )
-                                            })?;
-                                        break Some(status);
-                                    }
-                                }
-                            }
+                    // Check if we should interrupt the running tests or the in-progress
+                    // reload. We can only check the reload once (the oneshot is consumed), and
+                    // only for interruptible reloads.
+                    let interrupt = if interrupt_tests && *tests_running.borrow() {
+                        tracing::debug!("Interrupting tests");
+                        true
+                    } else if interrupt_reloads {
+                        match reload_receiver.take() {
+                            Some(reload_receiver) => should_interrupt(reload_receiver).await,
+                            None => false,
                         }
-                    }
+                    } else {
+                        false
+                    };
 
-                    // Either `interrupt_reloads` is `false`, the `reload_receiver` was already
-                    // consumed, or `should_interrupt` returned false. Accumulate the event
-                    // and keep waiting for the dispatch task to finish.
+                    // Accumulate the event, and keep waiting for the dispatch task to finish
+                    // unless we're interrupting it.
                     match pending_event {
                         Some(ref mut pending_event) => pending_event.merge(new_event),
                         None => pending_event = Some(new_event),
                     }
+                    if !interrupt {
+                        // Loop around to make sure we keep waiting for the `task`.
+                        continue;
+                    }
 
-                    // Loop around to make sure we keep waiting for the `task`.
-                    continue;
+                    // Merge everything: any previously accumulated events plus the newest
+                    // event.
+                    if let Some(pending_event) = pending_event.take() {
+                        event.merge(pending_event);
+                    }
+                    match interrupt_dispatch(&mut task, ghci, exited_receiver).await? {
+                        None => return Ok(HandleResult::Interrupted(event)),
+                        Some(status) => {
+                            pending_event = Some(event);
+                            break Some(status);
+                        }
+                    }
+                }
+                Ok(()) = tests_running.changed(), if interrupt_tests && pending_event.is_some() => {
+                    // Files changed before the tests started, so don't bother finishing them.
+                    if !*tests_running.borrow_and_update() {
+                        continue;
+                    }
+                    tracing::debug!("Interrupting tests");
+                    if let Some(pending_event) = pending_event.take() {
+                        event.merge(pending_event);
+                    }
+                    match interrupt_dispatch(&mut task, ghci, exited_receiver).await? {
+                        None => return Ok(HandleResult::Interrupted(event)),
+                        Some(status) => {
+                            pending_event = Some(event);
+                            break Some(status);
+                        }
+                    }
                 }
                 ret = &mut task => {
                     match ret? {
@@ -450,6 +463,38 @@ impl GhciManager {
     }
 }
 
+/// Cancel the in-progress dispatch `task` and interrupt `ghci`.
+///
+/// Returns the exit status if `ghci` was killed because it couldn't be interrupted cleanly.
+async fn interrupt_dispatch(
+    task: &mut JoinHandle<eyre::Result<()>>,
+    ghci: &Mutex<Ghci>,
+    exited_receiver: &mut mpsc::Receiver<ExitStatus>,
+) -> eyre::Result<Option<ExitStatus>> {
+    // Cancel the in-progress reload. This releases the `ghci` lock to prevent a deadlock.
+    task.abort();
+
+    // Send a SIGINT to interrupt the reload.
+    // NB: This may take a couple seconds to register.
+    match ghci.lock().await.send_sigint().await {
+        Ok(()) => Ok(None),
+        Err(e) => {
+            // `send_sigint` may kill the session if it cannot leave ghci in a usable state (e.g.
+            // sync barrier failure). Wait for the exit and route through the standard restart
+            // path with the merged event preserved.
+            tracing::warn!(
+                error = ?e,
+                "Failed to interrupt ghci; session was killed for restart",
+            );
+            let status = exited_receiver
+                .recv()
+                .await
+                .ok_or_else(|| eyre::eyre!("ghci exit channel closed after kill"))?;
+            Ok(Some(status))
+        }
+    }
+}
+
 /// Drain all pending events from the receiver and merge them into `event`.
 fn drain_pending(event: &mut WatcherEvent, watcher_receiver: &mut mpsc::Receiver<WatcherEvent>) {
     while let Ok(new_event) = watcher_receiver.try_recv() {
@@ -12,12 +12,13 @@ use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::io::IsTerminal;
 use std::process::ExitStatus;
+use std::process::Output;
 use std::process::Stdio;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use tokio::io::DuplexStream;
 use tokio::sync::oneshot;
-use tokio::task::JoinHandle;
 
 use aho_corasick::AhoCorasick;
 use backoff::backoff::Backoff;
@@ -30,6 +31,7 @@ use nix::unistd::Pid;
 use tokio::io::AsyncBufReadExt;
 use tokio::io::BufReader;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tracing::instrument;
 
 mod stdin;
@@ -50,10 +52,20 @@ mod error_log;
 use error_log::ErrorLog;
 
 pub mod parse;
-use parse::parse_eval_commands;
+use parse::parse_file_eval_commands;
+use parse::parse_imports;
+use parse::update_expected_output;
 use parse::CompilationResult;
+use parse::DiagnosticFormat;
 use parse::EvalCommand;
+use parse::EvalResult;
+use parse::EvalStatus;
+use parse::EvalSyntax;
+use parse::GhcDiagnostic;
+use parse::Severity;
 use parse::ShowPaths;
+use parse::TestFailure;
+use parse::TestResults;
 
 mod ghci_command;
 pub use ghci_command::GhciCommand;
@@ -65,18 +77,32 @@ mod writer;
 use crate::buffers::GHCI_BUFFER_CAPACITY;
 pub use crate::ghci::writer::GhciWriter;
 use crate::haskell_source_file::is_haskell_source_file;
+use crate::placeholders::Placeholders;
 
 mod progress_writer;
 
 mod module_set;
 pub use module_set::ModuleSet;
 
+mod dependent_files;
+pub use dependent_files::DependentFiles;
+
 mod file_classifier;
+use file_classifier::Classification;
 pub use file_classifier::FileClassifier;
 use file_classifier::ReloadActions;
 
+mod explain;
+pub use explain::run_explain;
+
 mod loaded_module;
+
+mod test_selection;
 use loaded_module::LoadedModule;
+pub use test_selection::SpecModuleFormat;
+
+mod written_files;
+pub use written_files::WrittenFiles;
 
 use crate::aho_corasick::AhoCorasickExt;
 use crate::buffers::LINE_BUFFER_CAPACITY;
@@ -86,11 +112,14 @@ use crate::clonable_command::ClonableCommand;
 use crate::event_filter::FileEvent;
 use crate::format_bulleted_list;
 use crate::hooks;
+use crate::hooks::HookHandles;
 use crate::hooks::HookOpts;
 use crate::hooks::LifecycleEvent;
 use crate::ignore::GlobMatcher;
+use crate::ignore::IgnoreFiles;
 use crate::incremental_reader::IncrementalReader;
 use crate::normal_path::NormalPath;
+use crate::path_map::PathMap;
 use crate::shutdown::ShutdownHandle;
 use crate::CommandExt;
 use crate::StringCase;
@@ -113,6 +142,19 @@ pub struct GhciOpts {
     pub error_path: Option<Utf8PathBuf>,
     /// Enable running eval commands in files.
     pub enable_eval: bool,
+    /// How eval commands are written in files, including whether doctest-style `-- >>>` examples
+    /// are evaluated.
+    pub eval_syntax: EvalSyntax,
+    /// Rewrite the expected output of eval commands when it doesn't match.
+    pub update_eval_output: bool,
+    /// Write the output of every eval command into the file beneath it.
+    pub write_eval_output: bool,
+    /// Run eval commands in every file after reloads, not just in affected modules.
+    pub eval_all: bool,
+    /// A path to write the results of eval commands to.
+    pub eval_results_path: Option<Utf8PathBuf>,
+    /// Interrupt eval commands which take longer than this.
+    pub eval_timeout: Option<Duration>,
     /// Extra directories to add to the module import search paths parsed from `:show paths`,
     /// used for converting module paths to module names and vice versa.
     pub extra_search_paths: Vec<Utf8PathBuf>,
@@ -122,14 +164,36 @@ pub struct GhciOpts {
     pub restart_globs: GlobMatcher,
     /// Reload the `ghci` session when paths matching these globs are changed.
     pub reload_globs: GlobMatcher,
+    /// Ignore changes to paths ignored by `.gitignore` and similar files.
+    pub ignore_files: Option<IgnoreFiles>,
     /// Determines whether we should interrupt a reload in progress or not.
     pub interrupt_reloads: bool,
+    /// Determines whether we should interrupt running tests when files change.
+    pub interrupt_tests: bool,
     /// Where to write what `ghci` emits to `stdout`. Inherits parent's `stdout` by default.
     pub stdout_writer: GhciWriter,
     /// Where to write what `ghci` emits to `stderr`. Inherits parent's `stderr` by default.
     pub stderr_writer: GhciWriter,
     /// Whether to clear the screen before reloads and restarts.
     pub clear: bool,
+    /// Naming convention for spec modules, if only tests for recompiled modules should be run.
+    pub test_selection: Option<SpecModuleFormat>,
+    /// Files written by `ghciwatch`, shared with [`FileClassifier`]s so that changes written by
+    /// `ghciwatch` don't trigger reloads.
+    pub written_files: WrittenFiles,
+    /// Files which modules depend on at compile time, shared with [`FileClassifier`]s so that
+    /// changes to them reload the modules which depend on them.
+    pub dependent_files: DependentFiles,
+    /// Mappings between paths `ghci` sees and paths `ghciwatch` sees.
+    pub path_map: PathMap,
+    /// Whether to watch the paths derived from `ghci`'s module search paths.
+    pub watch_search_paths: bool,
+    /// If given, paths for the watcher to watch in addition to the `--watch` paths are sent here:
+    /// the files modules depend on at compile time, and the paths derived from `ghci`'s module
+    /// search paths if `watch_search_paths` is set.
+    pub watch_paths: Option<Arc<watch::Sender<BTreeSet<Utf8PathBuf>>>>,
+    /// If given, `true` is sent here once `ghci` first finishes loading.
+    pub ready: Option<Arc<watch::Sender<bool>>>,
 }
 
 impl GhciOpts {
@@ -201,6 +265,18 @@ impl GhciOpts {
                 command,
                 error_path: opts.error_file.clone(),
                 enable_eval: opts.enable_eval,
+                eval_syntax: EvalSyntax {
+                    line_marker: opts.eval_line_marker.clone(),
+                    multiline_start_marker: opts.eval_multiline_start_marker.clone(),
+                    multiline_end_marker: opts.eval_multiline_end_marker.clone(),
+                    output_marker: opts.eval_output_marker.clone(),
+                    doctests: opts.eval_doctests,
+                },
+                update_eval_output: opts.update_eval_output,
+                write_eval_output: opts.write_eval_output,
+                eval_all: opts.eval_all,
+                eval_results_path: opts.eval_results_file.clone(),
+                eval_timeout: opts.eval_timeout,
                 extra_search_paths: opts
                     .extra_module_search_paths
                     .iter()
@@ -209,10 +285,19 @@ impl GhciOpts {
                 hooks: opts.hooks.clone(),
                 restart_globs: opts.watch.restart_globs()?,
                 reload_globs: opts.watch.reload_globs()?,
+                ignore_files: opts.watch.ignore_files(),
                 interrupt_reloads: opts.interrupt_reloads(),
+                interrupt_tests: opts.test.interrupt_tests,
                 stdout_writer,
                 stderr_writer,
                 clear: opts.clear,
+                test_selection: opts.test.test_selection()?,
+                written_files: Default::default(),
+                dependent_files: Default::default(),
+                path_map: PathMap::new(opts.path_maps.clone()),
+                watch_search_paths: opts.watch.watch_search_paths,
+                watch_paths: None,
+                ready: None,
             },
             tui_reader,
         ))
@@ -223,7 +308,13 @@ impl GhciOpts {
     /// The classifier uses the process's current working directory. Call
     /// [`FileClassifier::set_cwd`] after GHCi initialization to update it.
     pub fn file_classifier(&self) -> eyre::Result<FileClassifier> {
-        FileClassifier::new(self.restart_globs.clone(), self.reload_globs.clone())
+        FileClassifier::new(
+            self.restart_globs.clone(),
+            self.reload_globs.clone(),
+            self.ignore_files.clone(),
+            self.written_files.clone(),
+            self.dependent_files.clone(),
+        )
     }
 
     #[instrument(skip_all, level = "trace")]
@@ -275,10 +366,18 @@ pub struct Ghci {
     targets: ModuleSet,
     /// Eval commands, if `opts.enable_eval` is set.
     eval_commands: BTreeMap<NormalPath, Vec<EvalCommand>>,
+    /// The latest results of the eval commands in each file.
+    eval_results: BTreeMap<NormalPath, Vec<EvalResult>>,
     /// Search paths / current working directory for this `ghci` session.
     search_paths: ShowPaths,
     /// Tasks running `async:` shell commands in the background.
-    command_handles: Vec<JoinHandle<eyre::Result<ExitStatus>>>,
+    command_handles: HookHandles,
+    /// Paths changed by the current reload or restart, for placeholders in hook commands.
+    changed_files: Vec<NormalPath>,
+    /// Whether the test hooks are running, so that the tests can be interrupted when files change.
+    tests_running: watch::Sender<bool>,
+    /// Whether `--select-tests` has replaced the arguments the session started with.
+    tests_selected: bool,
     /// Monotonic counter for generating unique sync barrier nonces.
     sync_nonce: u64,
 }
@@ -302,15 +401,16 @@ impl Ghci {
         opts: GhciOpts,
         exited_sender: mpsc::Sender<ExitStatus>,
     ) -> eyre::Result<Self> {
-        let mut command_handles = Vec::new();
+        let mut command_handles = HookHandles::default();
         {
             let span = tracing::debug_span!("before_startup_shell");
             let _enter = span.enter();
+            let placeholders = Placeholders {
+                error_file: opts.error_path.as_ref().map(ToString::to_string),
+                ..Placeholders::new(LifecycleEvent::Startup(hooks::When::Before))
+            };
             opts.hooks
-                .run_shell_hooks(
-                    LifecycleEvent::Startup(hooks::When::Before),
-                    &mut command_handles,
-                )
+                .run_shell_hooks(&placeholders, &mut command_handles)
                 .await?;
         }
 
@@ -358,9 +458,14 @@ impl Ghci {
             stderr_sender: stderr_sender.clone(),
             buffer: vec![0; LINE_BUFFER_CAPACITY],
             prompt_patterns: AhoCorasick::from_anchored_patterns([PROMPT]),
+            path_map: opts.path_map.clone(),
+            parse_test_results: false,
         };
 
-        let stdin = GhciStdin { stdin };
+        let stdin = GhciStdin {
+            stdin,
+            path_map: opts.path_map.clone(),
+        };
 
         shutdown
             .spawn("stderr", |shutdown| {
@@ -393,8 +498,7 @@ impl Ghci {
             Some(error_path) => Some(NormalPath::from_cwd(error_path)?),
             None => None,
         });
-        let classifier =
-            FileClassifier::new(opts.restart_globs.clone(), opts.reload_globs.clone())?;
+        let classifier = opts.file_classifier()?;
         let extra_search_paths = opts.extra_search_paths.clone();
 
         Ok(Ghci {
@@ -409,11 +513,15 @@ impl Ghci {
             classifier,
             targets: Default::default(),
             eval_commands: Default::default(),
+            eval_results: Default::default(),
             search_paths: ShowPaths {
                 cwd: crate::current_dir_utf8()?,
                 search_paths: extra_search_paths,
             },
             command_handles,
+            changed_files: Vec::new(),
+            tests_running: watch::channel(false).0,
+            tests_selected: false,
             sync_nonce: 0,
         })
     }
@@ -461,6 +569,14 @@ impl Ghci {
         self.refresh_targets().await?;
         // Get the initial list of eval commands.
         self.refresh_eval_commands().await?;
+        // Get the initial set of dependent files.
+        self.opts.dependent_files.clear();
+        let targets = self
+            .targets
+            .iter()
+            .map(|target| target.path().clone())
+            .collect::<Vec<_>>();
+        self.refresh_dependent_files_for_paths(&targets).await;
 
         Ok(())
     }
@@ -469,6 +585,19 @@ impl Ghci {
         self.classifier.classify(events, &self.targets)
     }
 
+    /// Classify a change to the given path, as if it had just been modified (or removed, if it
+    /// doesn't exist).
+    fn explain(&self, path: &NormalPath) -> eyre::Result<(NormalPath, Classification)> {
+        let event = if path.exists() {
+            FileEvent::Modify(path.absolute().to_owned())
+        } else {
+            FileEvent::Remove(path.absolute().to_owned())
+        };
+        let path = self.classifier.relative_path(path.absolute())?;
+        let classification = self.classifier.classify_path(&event, &path, &self.targets);
+        Ok((path, classification))
+    }
+
     /// Reload this `ghci` session to include the given modified and removed paths.
     ///
     /// This may fully restart the `ghci` process.
@@ -484,6 +613,7 @@ impl Ghci {
         let start_instant = Instant::now();
         let actions = self.get_reload_actions(events)?;
         let _ = kind_sender.send(actions.kind());
+        self.changed_files = actions.changed_paths();
 
         if actions.needs_restart() {
             self.opts.clear();
@@ -507,6 +637,16 @@ impl Ghci {
                 .await?;
         }
 
+        if actions.needs_force_recompile {
+            // GHC doesn't know about some of the changed files, so it would consider the modules
+            // which depend on them up to date. There's no way to force recompiling only those
+            // modules, so this recompiles everything.
+            tracing::info!("Forcing recompilation for changed files which GHC doesn't track");
+            self.stdin
+                .set_force_recomp(&mut self.stdout, true, &mut log)
+                .await?;
+        }
+
         if !actions.needs_remove.is_empty() {
             tracing::info!(
                 "Removing modules from ghci:\n{}",
@@ -531,6 +671,14 @@ impl Ghci {
             self.stdin.reload(&mut self.stdout, &mut log).await?;
             self.refresh_eval_commands_for_paths(&actions.needs_reload)
                 .await?;
+            self.refresh_dependent_files_for_paths(&actions.needs_reload)
+                .await;
+        }
+
+        if actions.needs_force_recompile {
+            self.stdin
+                .set_force_recomp(&mut self.stdout, false, &mut log)
+                .await?;
         }
 
         if actions.needs_modify() {
@@ -589,6 +737,18 @@ impl Ghci {
         events: [LifecycleEvent; N],
     ) -> eyre::Result<()> {
         self.stop().await?;
+        // The new session runs the before-startup hooks again, so cancel any superseded ones, and
+        // carry the old session's `async:` commands over so they can be cancelled later.
+        self.opts.hooks.cancel_superseded(
+            LifecycleEvent::Startup(hooks::When::Before),
+            &mut self.command_handles,
+        );
+        let command_handles = std::mem::take(&mut self.command_handles);
+        let changed_files = std::mem::take(&mut self.changed_files);
+        // Keep the same channel so that subscribers see the new session's tests. If `ghci` exited
+        // while running tests, the task running them was canceled before it could clear this.
+        let tests_running = std::mem::replace(&mut self.tests_running, watch::channel(false).0);
+        tests_running.send_replace(false);
         let new = Self::new(
             self.shutdown.clone(),
             self.opts.clone(),
@@ -596,25 +756,145 @@ impl Ghci {
         )
         .await?;
         let _ = std::mem::replace(self, new);
+        self.command_handles.append(command_handles);
+        self.changed_files = changed_files;
+        self.tests_running = tests_running;
         self.initialize(log, events).await?;
 
         Ok(())
     }
 
+    /// Subscribe to changes in whether the test hooks are running.
+    pub fn subscribe_tests_running(&self) -> watch::Receiver<bool> {
+        self.tests_running.subscribe()
+    }
+
     /// Run the user provided test command.
+    ///
+    /// NOTE: With `--interrupt-tests`, this function may be canceled and dropped at any `await`
+    /// point!
     #[instrument(skip_all, level = "debug")]
-    async fn test(&mut self, log: &mut CompilationLog) -> eyre::Result<()> {
-        self.run_hooks(LifecycleEvent::Test, log).await?;
+    async fn test(&mut self, log: &mut CompilationLog, event: LifecycleEvent) -> eyre::Result<()> {
+        self.tests_running.send_replace(true);
+        let ret = self.test_inner(log, event).await;
+        self.tests_running.send_replace(false);
+        ret
+    }
+
+    async fn test_inner(
+        &mut self,
+        log: &mut CompilationLog,
+        event: LifecycleEvent,
+    ) -> eyre::Result<()> {
+        // Only `ghci` test hooks can observe `:set args`.
+        if self
+            .opts
+            .hooks
+            .select(LifecycleEvent::Test(None))
+            .any(|hook| matches!(hook.command, hooks::Command::Ghci(_)))
+            && !self.select_tests(log, event).await?
+        {
+            return Ok(());
+        }
+        self.run_hooks(LifecycleEvent::Test(Some(hooks::When::Before)), log)
+            .await?;
+        let test_results = log.test_results.len();
+        self.run_hooks(LifecycleEvent::Test(None), log).await?;
+        self.add_test_failure_diagnostics(test_results, log).await?;
+        self.run_hooks(LifecycleEvent::Test(Some(hooks::When::After)), log)
+            .await?;
         Ok(())
     }
 
+    /// Add diagnostics for failing tests in the test results after `test_results` to the
+    /// compilation log.
+    async fn add_test_failure_diagnostics(
+        &mut self,
+        test_results: usize,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<()> {
+        let diagnostics = log.test_results[test_results..]
+            .iter()
+            .flat_map(|results| results.failures.iter().map(TestFailure::to_diagnostic))
+            .collect();
+        self.add_diagnostics(diagnostics, log).await
+    }
+
+    /// Select the tests to run for the modules compiled in this reload with `:set args`, if
+    /// enabled.
+    ///
+    /// After startup and restarts, and after reloads which didn't recompile any modules, all the
+    /// tests are run. Returns `false` if the tests should be skipped because a reload recompiled
+    /// modules but affected no specs.
+    async fn select_tests(
+        &mut self,
+        log: &mut CompilationLog,
+        event: LifecycleEvent,
+    ) -> eyre::Result<bool> {
+        let format = match &self.opts.test_selection {
+            Some(format) => format,
+            None => return Ok(true),
+        };
+
+        if !matches!(event, LifecycleEvent::Reload(_)) {
+            // This is a new session, so we haven't changed any arguments set in `.ghci`.
+            tracing::info!("Selected all tests");
+            return Ok(true);
+        }
+
+        if log.compiled_modules.is_empty() {
+            // We don't know what changed, so run everything.
+            tracing::info!("No modules recompiled, selected all tests");
+            if self.tests_selected {
+                self.stdin
+                    .run_command(&mut self.stdout, &GhciCommand(":set args".to_owned()), log)
+                    .await?;
+                self.tests_selected = false;
+            }
+            return Ok(true);
+        }
+
+        let loaded_modules = self
+            .targets
+            .iter()
+            .filter_map(|module| self.search_paths.path_to_module(module.path()).ok())
+            .collect();
+        let spec_modules = format.select(
+            log.compiled_modules.iter().map(String::as_str),
+            &loaded_modules,
+        );
+
+        if spec_modules.is_empty() {
+            tracing::info!("No affected specs, skipping tests");
+            return Ok(false);
+        }
+
+        tracing::info!("Selected tests:\n{}", format_bulleted_list(&spec_modules));
+        let command = format!(
+            ":set args {}",
+            format.hspec_match_args(&spec_modules).join(" ")
+        );
+        self.stdin
+            .run_command(&mut self.stdout, &GhciCommand(command), log)
+            .await?;
+        self.tests_selected = true;
+
+        Ok(true)
+    }
+
     /// Run the eval commands, if enabled.
+    ///
+    /// After reloads, only eval commands in modules affected by the reload are run, unless
+    /// `--eval-all` is given. After startup and restarts, all the eval commands are run.
     #[instrument(skip_all, level = "debug")]
-    async fn eval(&mut self, log: &mut CompilationLog) -> eyre::Result<()> {
+    async fn eval(&mut self, log: &mut CompilationLog, event: LifecycleEvent) -> eyre::Result<()> {
         if !self.opts.enable_eval {
             return Ok(());
         }
 
+        let mut diagnostics = Vec::new();
+        let mut skipped = Vec::new();
+
         // TODO: This `clone` is ugly but I can't get the borrow checker to accept it otherwise.
         // Might be more efficient to swap it out for a default, but then it gets trickier to
         // restore the old value when the function returns.
@@ -624,6 +904,14 @@ impl Ghci {
                 continue;
             }
 
+            if matches!(event, LifecycleEvent::Reload(_))
+                && !self.opts.eval_all
+                && !self.is_affected(&path, &log.compiled_modules).await?
+            {
+                skipped.push(path);
+                continue;
+            }
+
             // If the `module` was already compiled, `ghci` may have loaded the interface file instead
             // of the interpreted bytecode, giving us this error message when we attempt to
             // load the top-level scope with `:module + *{module}`:
@@ -639,17 +927,181 @@ impl Ghci {
             self.stdin
                 .add_module_to_scope(&mut self.stdout, &module, log)
                 .await?;
-            for command in commands {
+            let write_output = self.opts.update_eval_output || self.opts.write_eval_output;
+            let mut outputs = Vec::new();
+            let mut results = Vec::with_capacity(commands.len());
+            for command in &commands {
                 tracing::info!("Eval {path}:{command}");
-                self.stdin
-                    .run_command(&mut self.stdout, &command.command, log)
-                    .await?;
+                let output = self
+                    .stdin
+                    .run_command_output(&mut self.stdout, &command.command, log);
+                let output = match self.opts.eval_timeout {
+                    None => output.await?,
+                    Some(timeout) => match tokio::time::timeout(timeout, output).await {
+                        Ok(output) => output?,
+                        Err(_) => {
+                            tracing::error!("Eval {path}:{command} timed out after {timeout:.2?}");
+                            diagnostics.push(command.timeout_diagnostic(path.absolute(), timeout));
+                            results.push(EvalResult {
+                                status: EvalStatus::Timeout,
+                                ..command.result(path.absolute(), "")
+                            });
+                            if let Err(err) = self.send_sigint().await {
+                                // The session can't be used anymore, so make sure it's dead; the
+                                // manager will restart it.
+                                tracing::error!("Failed to interrupt eval command: {err:?}");
+                                if let Err(err) =
+                                    signal::killpg(self.process_group_id, Signal::SIGKILL)
+                                {
+                                    tracing::debug!("Failed to kill ghci: {err}");
+                                }
+                                self.eval_results.insert(path, results);
+                                self.add_diagnostics(diagnostics, log).await?;
+                                return Ok(());
+                            }
+                            continue;
+                        }
+                    },
+                };
+
+                let result = command.result(path.absolute(), &output);
+                tracing::debug!(
+                    path = %result.path,
+                    line = result.line,
+                    column = result.column,
+                    command = result.command,
+                    output = result.output,
+                    status = %result.status,
+                    "Eval result"
+                );
+                results.push(result);
+
+                if !command.has_expected_output() {
+                    if self.opts.write_eval_output {
+                        outputs.push((command, output));
+                    }
+                    continue;
+                }
+                if let Some(diagnostic) = command.check_output(path.absolute(), &output) {
+                    if write_output {
+                        outputs.push((command, output));
+                    } else {
+                        tracing::error!("Eval {path}:{command} output doesn't match");
+                        diagnostics.push(diagnostic);
+                    }
+                }
             }
             self.stdin
                 .remove_module_from_scope(&mut self.stdout, &module, log)
                 .await?;
+            self.write_eval_output(&path, &commands, &outputs).await?;
+            self.eval_results.insert(path, results);
         }
 
+        if !skipped.is_empty() {
+            tracing::info!(
+                "Skipped eval commands in modules not affected by this reload:\n{}",
+                format_bulleted_list(&skipped)
+            );
+        }
+
+        self.write_eval_results().await?;
+        self.add_diagnostics(diagnostics, log).await?;
+
+        Ok(())
+    }
+
+    /// Write the latest results of the eval commands to `--eval-results-file`, if given.
+    async fn write_eval_results(&mut self) -> eyre::Result<()> {
+        let Some(path) = &self.opts.eval_results_path else {
+            return Ok(());
+        };
+
+        // Forget the results for files which no longer have eval commands.
+        let eval_commands = &self.eval_commands;
+        self.eval_results.retain(|path, _| {
+            eval_commands
+                .get(path)
+                .is_some_and(|commands| !commands.is_empty())
+        });
+
+        let results = self.eval_results.values().flatten().collect::<Vec<_>>();
+        let contents = serde_json::to_string_pretty(&results)?;
+        tokio::fs::write(path, contents)
+            .await
+            .wrap_err_with(|| format!("Failed to write eval results to {path}"))?;
+        tracing::debug!(%path, results = results.len(), "Wrote eval results");
+
+        Ok(())
+    }
+
+    /// Is the module at `path` one of the `compiled_modules`, or does it import one of them?
+    async fn is_affected(
+        &self,
+        path: &NormalPath,
+        compiled_modules: &[String],
+    ) -> eyre::Result<bool> {
+        let module = self.search_paths.path_to_module(path)?;
+        if compiled_modules.contains(&module) {
+            return Ok(true);
+        }
+
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("Failed to read {path}"))?;
+        let imports = parse_imports(&contents);
+        Ok(compiled_modules
+            .iter()
+            .any(|module| imports.contains(module)))
+    }
+
+    /// Write the `outputs` of eval commands into the file at `path`, replacing their existing
+    /// output.
+    ///
+    /// The file is left alone if it's changed since its eval `commands` were parsed, or if the
+    /// output in it is already up to date. Otherwise, its eval commands are parsed again from the
+    /// new contents, because we don't get a file event for our own write.
+    async fn write_eval_output(
+        &mut self,
+        path: &NormalPath,
+        commands: &[EvalCommand],
+        outputs: &[(&EvalCommand, String)],
+    ) -> eyre::Result<()> {
+        if outputs.is_empty() {
+            return Ok(());
+        }
+
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("Failed to read {path}"))?;
+        if parse_file_eval_commands(path, &contents, &self.opts.eval_syntax)? != commands {
+            tracing::warn!("{path} changed during eval; not writing eval output");
+            return Ok(());
+        }
+
+        let new_contents = update_expected_output(
+            &contents,
+            outputs
+                .iter()
+                .map(|(command, output)| (*command, output.as_str())),
+            &self.opts.eval_syntax.output_marker,
+        );
+        if new_contents == contents {
+            // Writing the file would change its modification time and produce a file event.
+            tracing::debug!("Eval output in {path} is up to date");
+            return Ok(());
+        }
+        let contents = new_contents;
+        self.opts.written_files.write(path, &contents).await?;
+        self.eval_commands.insert(
+            path.clone(),
+            parse_file_eval_commands(path, &contents, &self.opts.eval_syntax)?,
+        );
+        tracing::info!(
+            "Updated output of {} eval commands in {path}",
+            outputs.len()
+        );
+
         Ok(())
     }
 
@@ -676,9 +1128,29 @@ impl Ghci {
         }
         self.classifier.set_cwd(self.search_paths.cwd.clone());
         tracing::debug!(cwd = %self.search_paths.cwd, search_paths = ?self.search_paths.search_paths, "Parsed paths");
+        self.send_watch_paths();
         Ok(())
     }
 
+    /// Send the paths to watch in addition to the `--watch` paths to the watcher, if they've
+    /// changed.
+    fn send_watch_paths(&self) {
+        if let Some(watch_paths) = &self.opts.watch_paths {
+            let mut new_watch_paths = self.opts.dependent_files.files();
+            if self.opts.watch_search_paths {
+                new_watch_paths.extend(self.search_paths.watch_paths());
+            }
+            watch_paths.send_if_modified(|watch_paths| {
+                if *watch_paths == new_watch_paths {
+                    false
+                } else {
+                    *watch_paths = new_watch_paths;
+                    true
+                }
+            });
+        }
+    }
+
     /// Refresh `eval_commands` by reading and parsing the files in `targets`.
     #[instrument(skip_all, level = "debug")]
     async fn refresh_eval_commands(&mut self) -> eyre::Result<()> {
@@ -690,7 +1162,7 @@ impl Ghci {
 
         for target in self.targets.iter() {
             // Note: Loaded targets are always Haskell modules.
-            let commands = Self::parse_eval_commands(target.path()).await?;
+            let commands = Self::parse_eval_commands(target.path(), &self.opts.eval_syntax).await?;
             if !commands.is_empty() {
                 eval_commands.insert(target.path().clone(), commands);
             }
@@ -739,7 +1211,7 @@ impl Ghci {
                 continue;
             }
 
-            let commands = Self::parse_eval_commands(path).await?;
+            let commands = Self::parse_eval_commands(path, &self.opts.eval_syntax).await?;
             if commands.is_empty() {
                 self.eval_commands.remove(path);
             } else {
@@ -767,15 +1239,37 @@ impl Ghci {
 
     /// Read and parse eval commands from the given `path`.
     #[instrument(level = "trace")]
-    async fn parse_eval_commands(path: &Utf8Path) -> eyre::Result<Vec<EvalCommand>> {
+    async fn parse_eval_commands(
+        path: &Utf8Path,
+        syntax: &EvalSyntax,
+    ) -> eyre::Result<Vec<EvalCommand>> {
         let contents = tokio::fs::read_to_string(path)
             .await
             .wrap_err_with(|| format!("Failed to read {path}"))?;
-        let commands = parse_eval_commands(&contents)
+        let commands = parse_file_eval_commands(path, &contents, syntax)
             .wrap_err_with(|| format!("Failed to parse eval commands from file {path}"))?;
         Ok(commands)
     }
 
+    /// Refresh the files the given modules depend on at compile time by reading and parsing them.
+    #[instrument(skip_all, level = "debug")]
+    async fn refresh_dependent_files_for_paths(&self, paths: &[NormalPath]) {
+        for path in paths.iter().filter(|path| is_haskell_source_file(path)) {
+            match tokio::fs::read_to_string(path).await {
+                Ok(contents) => {
+                    self.opts
+                        .dependent_files
+                        .update(path, &contents, &self.search_paths.cwd);
+                }
+                Err(err) => {
+                    tracing::debug!(%path, "Failed to read module: {err}");
+                    self.opts.dependent_files.remove(path);
+                }
+            }
+        }
+        self.send_watch_paths();
+    }
+
     /// `:add` a module or modules to the GHCi session.
     #[instrument(skip(self), level = "debug")]
     async fn add_modules(
@@ -851,6 +1345,7 @@ impl Ghci {
         self.targets.extend(modules);
 
         self.refresh_eval_commands_for_paths(paths).await?;
+        self.refresh_dependent_files_for_paths(paths).await;
 
         Ok(())
     }
@@ -877,6 +1372,8 @@ impl Ghci {
 
         self.refresh_eval_commands_for_paths(std::iter::once(path))
             .await?;
+        self.refresh_dependent_files_for_paths(std::slice::from_ref(path))
+            .await;
 
         Ok(())
     }
@@ -905,6 +1402,9 @@ impl Ghci {
         }
 
         self.clear_eval_commands_for_paths(paths).await;
+        for path in paths {
+            self.opts.dependent_files.remove(path);
+        }
 
         Ok(())
     }
@@ -928,6 +1428,9 @@ impl Ghci {
     #[instrument(skip_all, level = "debug")]
     async fn send_sigint(&mut self) -> eyre::Result<()> {
         let start_instant = Instant::now();
+        // If we interrupted the tests, the task running them was canceled before it could clear
+        // this.
+        self.tests_running.send_replace(false);
 
         // Phase 1: Send SIGINT repeatedly until we find a clean, uninterrupted prompt.
         //
@@ -1078,7 +1581,7 @@ impl Ghci {
     // Get rid of any handles for background commands that have finished.
     #[instrument(skip_all, level = "trace")]
     fn prune_command_handles(&mut self) {
-        self.command_handles.retain(|handle| !handle.is_finished());
+        self.command_handles.prune();
     }
 
     /// Finish a compilation process.
@@ -1103,9 +1606,17 @@ impl Ghci {
             self.run_hooks(event, log).await?;
         }
 
+        let failed = matches!(log.result(), Some(CompilationResult::Err));
+
+        if failed {
+            self.run_hooks(LifecycleEvent::Failure, log).await?;
+        } else {
+            self.run_hooks(LifecycleEvent::Success, log).await?;
+        }
+
         let event = events[N - 1];
 
-        if let Some(CompilationResult::Err) = log.result() {
+        if failed {
             tracing::error!(
                 "{} failed in {:.2?}",
                 event.event_noun().first_char_to_ascii_uppercase(),
@@ -1119,9 +1630,9 @@ impl Ghci {
                 compilation_start.elapsed()
             );
             // Run the eval commands, if any.
-            self.eval(log).await?;
+            self.eval(log, event).await?;
             // Run the user-provided test command, if any.
-            self.test(log).await?;
+            self.test(log, event).await?;
         }
 
         Ok(())
@@ -1133,20 +1644,89 @@ impl Ghci {
         event: LifecycleEvent,
         log: &mut CompilationLog,
     ) -> eyre::Result<()> {
-        for hook in self.opts.hooks.select(event) {
+        self.opts
+            .hooks
+            .cancel_superseded(event, &mut self.command_handles);
+        let timeout = self.opts.hooks.timeout(event);
+        let placeholders = self.placeholders(event);
+        // We expand (and therefore clone) the hooks so that we can interrupt `ghci` if a command
+        // times out.
+        let hooks = self
+            .opts
+            .hooks
+            .select(event)
+            .map(|hook| hooks::Hook {
+                event: hook.event,
+                command: hook.command.expand(&placeholders),
+            })
+            .collect::<Vec<_>>();
+        for hook in hooks {
             tracing::info!(command = %hook.command, "Running {hook} command");
             match &hook.command {
                 hooks::Command::Ghci(command) => {
                     let start_time = Instant::now();
-                    self.stdin
-                        .run_command(&mut self.stdout, command, log)
-                        .await?;
-                    if let LifecycleEvent::Test = &hook.event {
-                        tracing::info!("Finished running tests in {:.2?}", start_time.elapsed());
+                    let test_results = log.test_results.len();
+                    self.stdout.parse_test_results =
+                        matches!(hook.event, LifecycleEvent::Test(None));
+                    let run = self.stdin.run_command(&mut self.stdout, command, log);
+                    let result = match timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, run).await,
+                        None => Ok(run.await),
+                    };
+                    self.stdout.parse_test_results = false;
+                    match result {
+                        Ok(result) => {
+                            result?;
+                            if let LifecycleEvent::Test(None) = &hook.event {
+                                log_test_results(
+                                    log.test_results[test_results..].last(),
+                                    start_time.elapsed(),
+                                );
+                            }
+                        }
+                        Err(_elapsed) => {
+                            let elapsed = start_time.elapsed();
+                            tracing::error!(
+                                %command,
+                                timed_out = true,
+                                "{hook} command timed out after {elapsed:.2?}",
+                            );
+                            self.add_timeout_diagnostic(&hook, elapsed, log).await?;
+                            if let Err(err) = self.send_sigint().await {
+                                // The session can't be used anymore, so make sure it's dead; the
+                                // manager will see `ghci` exit and restart it, so there's no
+                                // point in running any more hooks.
+                                tracing::error!("Failed to interrupt {hook} command: {err:?}");
+                                if let Err(err) =
+                                    signal::killpg(self.process_group_id, Signal::SIGKILL)
+                                {
+                                    tracing::debug!("Failed to kill ghci: {err}");
+                                }
+                                return Ok(());
+                            }
+                        }
                     }
                 }
                 hooks::Command::Shell(command) => {
-                    command.run_on(&mut self.command_handles).await?;
+                    let output = command
+                        .run_on(self.command_handles.for_event(event), timeout)
+                        .await?;
+                    if let Some(output) = output {
+                        if let Some(elapsed) = output.timed_out {
+                            self.add_timeout_diagnostic(&hook, elapsed, log).await?;
+                        }
+                        if let Some(format) = command.diagnostics {
+                            let diagnostics =
+                                self.parse_hook_diagnostics(&hook, format, &output.output)?;
+                            if event.is_after_compilation() {
+                                self.add_diagnostics(diagnostics, log).await?;
+                            } else {
+                                // These are relocated along with the compiler's diagnostics
+                                // when compilation finishes.
+                                log.diagnostics.extend(diagnostics);
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -1154,12 +1734,150 @@ impl Ghci {
         Ok(())
     }
 
+    /// Get the values for placeholders in hook commands run for the given event.
+    fn placeholders(&self, event: LifecycleEvent) -> Placeholders {
+        Placeholders {
+            event,
+            changed_files: self.changed_files.iter().map(ToString::to_string).collect(),
+            changed_modules: self
+                .changed_files
+                .iter()
+                .filter(|path| is_haskell_source_file(path))
+                .filter_map(|path| self.search_paths.path_to_module(path).ok())
+                .collect(),
+            error_file: self.error_log.path().map(ToString::to_string),
+        }
+    }
+
+    /// Mark the compilation log as failed because a hook timed out, and add a diagnostic saying
+    /// so to the error log.
+    async fn add_timeout_diagnostic(
+        &mut self,
+        hook: &hooks::Hook<hooks::Command>,
+        elapsed: Duration,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<()> {
+        log.mark_failed();
+        self.add_diagnostics(
+            vec![GhcDiagnostic {
+                severity: Severity::Error,
+                path: None,
+                span: Default::default(),
+                message: format!(
+                    "\n    {hook} command timed out after {elapsed:.2?}: {}\n",
+                    hook.command
+                ),
+            }],
+            log,
+        )
+        .await
+    }
+
+    /// Add diagnostics found after compilation finished to the compilation log, and rewrite the
+    /// error log if there are any.
+    ///
+    /// The diagnostics' paths should be relative to `ghci`'s working directory.
+    async fn add_diagnostics(
+        &mut self,
+        mut diagnostics: Vec<GhcDiagnostic>,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<()> {
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(error_log_dir) = self.error_log.path().and_then(|path| path.parent()) {
+            for diagnostic in diagnostics.iter_mut() {
+                diagnostic.make_relative_to(&self.search_paths.cwd, error_log_dir)?;
+            }
+        }
+        log.diagnostics.extend(diagnostics);
+        self.write_error_log(log).await?;
+
+        Ok(())
+    }
+
+    /// Parse diagnostics from a shell hook's output.
+    ///
+    /// Hooks run in our working directory, so their paths are made relative to `ghci`'s working
+    /// directory to match the compiler's diagnostics.
+    fn parse_hook_diagnostics(
+        &self,
+        hook: &hooks::Hook<hooks::Command>,
+        format: DiagnosticFormat,
+        output: &Output,
+    ) -> eyre::Result<Vec<GhcDiagnostic>> {
+        let mut diagnostics = match format.parse(output) {
+            Ok(diagnostics) => diagnostics,
+            Err(err) => {
+                tracing::warn!(
+                    command = %hook.command,
+                    error = ?err,
+                    "Failed to parse {format} output from {hook} command"
+                );
+                return Ok(Vec::new());
+            }
+        };
+
+        let cwd = crate::current_dir_utf8()?;
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.make_relative_to(&cwd, &self.search_paths.cwd)?;
+        }
+
+        tracing::debug!(
+            command = %hook.command,
+            diagnostics = diagnostics.len(),
+            "Parsed diagnostics from {hook} command"
+        );
+
+        Ok(diagnostics)
+    }
+
     #[instrument(skip(self), level = "trace")]
     async fn write_error_log(&mut self, log: &CompilationLog) -> eyre::Result<()> {
         self.error_log.write(log).await
     }
 }
 
+/// Log the time taken to run the tests, and the test results if they were parsed from the test
+/// runner's output.
+fn log_test_results(results: Option<&TestResults>, elapsed: Duration) {
+    let results = match results {
+        Some(results) => results,
+        None => {
+            tracing::info!("Finished running tests in {elapsed:.2?}");
+            return;
+        }
+    };
+
+    for failure in &results.failures {
+        tracing::info!(
+            test = failure.name,
+            path = failure.path.as_ref().map(|path| path.as_str()),
+            "Test failed: {}",
+            failure.name
+        );
+    }
+
+    if results.failed > 0 {
+        tracing::error!(
+            framework = %results.framework,
+            passed = results.passed,
+            failed = results.failed,
+            pending = results.pending,
+            "Finished running tests in {elapsed:.2?}: {results}"
+        );
+    } else {
+        tracing::info!(
+            framework = %results.framework,
+            passed = results.passed,
+            failed = results.failed,
+            pending = results.pending,
+            "Finished running tests in {elapsed:.2?}: {results}"
+        );
+    }
+}
+
 /// How a [`Ghci`] session responds to a reload event.
 #[derive(Debug)]
 pub enum GhciReloadKind {
@@ -0,0 +1,167 @@
+use winnow::ascii::space0;
+use winnow::combinator::alt;
+use winnow::combinator::delimited;
+use winnow::combinator::opt;
+use winnow::token::take_till;
+use winnow::PResult;
+use winnow::Parser;
+
+/// What a [`DependentFile`]'s path is relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependentFileBase {
+    /// The working directory, like paths given to `embedFile`.
+    WorkingDirectory,
+    /// The package's root directory, like paths given to `makeRelativeToProject`.
+    Project,
+    /// The module's directory, like paths given to `embedFileRelative`.
+    Module,
+}
+
+/// A file which a module depends on at compile time, found in a TemplateHaskell splice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependentFile {
+    /// The path, as written in the module.
+    pub path: String,
+    /// What the path is relative to.
+    pub base: DependentFileBase,
+    /// Does GHC track the file (with `addDependentFile`), so that `:reload` recompiles the module
+    /// when the file changes?
+    pub tracked: bool,
+}
+
+/// Functions which take the path of a file a module depends on at compile time, and whether they
+/// track the file with `addDependentFile`.
+const DEPENDENT_FILE_FUNCTIONS: [(&str, DependentFileBase, bool); 9] = [
+    (
+        "addDependentFile",
+        DependentFileBase::WorkingDirectory,
+        true,
+    ),
+    (
+        "qAddDependentFile",
+        DependentFileBase::WorkingDirectory,
+        true,
+    ),
+    ("embedFile", DependentFileBase::WorkingDirectory, true),
+    (
+        "embedFileIfExists",
+        DependentFileBase::WorkingDirectory,
+        true,
+    ),
+    ("embedStringFile", DependentFileBase::WorkingDirectory, true),
+    ("embedDir", DependentFileBase::WorkingDirectory, true),
+    ("embedFileRelative", DependentFileBase::Module, true),
+    ("makeRelativeToProject", DependentFileBase::Project, false),
+    // Only a compile-time dependency when run in a splice, like `runIO (readFile "x")`.
+    ("readFile", DependentFileBase::WorkingDirectory, false),
+];
+
+/// Find the files a Haskell module depends on at compile time, like `embedFile "data/x.json"`.
+///
+/// This only finds string literals passed directly to common functions from `template-haskell`
+/// and `file-embed`, and doesn't know about comments, so it may find extra files.
+///
+/// Paths given to functions which don't track files themselves, like `makeRelativeToProject`,
+/// are considered tracked if a function which does track files is used on the same line, like
+/// `makeRelativeToProject "x" >>= embedFile`.
+pub fn parse_dependent_files(contents: &str) -> Vec<DependentFile> {
+    let mut ret = Vec::new();
+    for line in contents.lines() {
+        let line_tracks_files = DEPENDENT_FILE_FUNCTIONS
+            .iter()
+            .any(|(function, _, tracked)| *tracked && line.contains(function));
+        for (function, base, tracked) in DEPENDENT_FILE_FUNCTIONS {
+            if function == "readFile" && !line.contains("runIO") {
+                continue;
+            }
+
+            for (index, _) in line.match_indices(function) {
+                // Don't match `myEmbedFile`, but do match `FileEmbed.embedFile`.
+                if line[..index]
+                    .chars()
+                    .next_back()
+                    .is_some_and(is_identifier_char)
+                {
+                    continue;
+                }
+
+                let mut rest = &line[index + function.len()..];
+                if let Ok(path) = path_argument.parse_next(&mut rest) {
+                    ret.push(DependentFile {
+                        path: path.to_owned(),
+                        base,
+                        tracked: tracked || line_tracks_files,
+                    });
+                }
+            }
+        }
+    }
+    ret
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+/// Parse a string literal path argument, like ` "data/x.json"`, ` $ "data/x.json"`, or
+/// ` ("data/x.json")`.
+fn path_argument<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    let _ = (space0, opt(alt(('$', '('))), space0).parse_next(input)?;
+    delimited('"', take_till(1.., ['"', '\\']), '"').parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_dependent_files() {
+        let file = |path: &str, base, tracked| DependentFile {
+            path: path.to_owned(),
+            base,
+            tracked,
+        };
+
+        assert_eq!(
+            parse_dependent_files(indoc!(
+                r#"
+                module MyLib where
+
+                import Data.FileEmbed qualified as FileEmbed
+
+                schema :: ByteString
+                schema = $(embedFile "data/schema.sql")
+
+                config :: ByteString
+                config = $(FileEmbed.embedFile $ "config.json")
+
+                templates = $(makeRelativeToProject "templates" >>= embedDir)
+
+                sibling = $(embedFileRelative ("Sibling.txt"))
+
+                readme = $(do
+                  addDependentFile "README.md"
+                  runIO (readFile "README.md"))
+
+                users = $(makeRelativeToProject "users.csv" >>= runIO . readFile >>= lift)
+
+                main = readFile "not-a-compile-time-dependency"
+                myEmbedFile "not-a-dependency"
+                embedFile someVariable
+                "#
+            )),
+            vec![
+                file("data/schema.sql", DependentFileBase::WorkingDirectory, true),
+                file("config.json", DependentFileBase::WorkingDirectory, true),
+                file("templates", DependentFileBase::Project, true),
+                file("Sibling.txt", DependentFileBase::Module, true),
+                file("README.md", DependentFileBase::WorkingDirectory, true),
+                file("README.md", DependentFileBase::WorkingDirectory, false),
+                file("users.csv", DependentFileBase::Project, false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Display;
+use std::process::Output;
+
+use winnow::combinator::alt;
+use winnow::PResult;
+use winnow::Parser;
+
+use super::parse_ghc_messages;
+use super::parse_hlint_json;
+use super::GhcDiagnostic;
+
+/// A format for diagnostics printed by a shell command, like a linter run as a lifecycle hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    /// GHC-style diagnostics, like `src/Foo.hs:4:11: warning: ...`, on stdout or stderr.
+    Ghc,
+    /// The JSON output of `hlint --json` on stdout.
+    HlintJson,
+}
+
+impl Display for DiagnosticFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticFormat::Ghc => write!(f, "ghc-diagnostics"),
+            DiagnosticFormat::HlintJson => write!(f, "hlint-json"),
+        }
+    }
+}
+
+impl DiagnosticFormat {
+    /// Parse diagnostics from a command's output.
+    pub fn parse(&self, output: &Output) -> eyre::Result<Vec<GhcDiagnostic>> {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match self {
+            DiagnosticFormat::Ghc => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let mut diagnostics = Vec::new();
+                for stream in [stdout, stderr] {
+                    diagnostics.extend(
+                        parse_ghc_messages(&stream)?
+                            .into_iter()
+                            .filter_map(|message| message.into_diagnostic()),
+                    );
+                }
+                Ok(diagnostics)
+            }
+            DiagnosticFormat::HlintJson => parse_hlint_json(&stdout),
+        }
+    }
+}
+
+/// Parse a diagnostic format prefix for a shell command, like `ghc-diagnostics:` or
+/// `hlint-json:`.
+pub fn diagnostic_format_prefix(input: &mut &str) -> PResult<DiagnosticFormat> {
+    alt((
+        "ghc-diagnostics:".value(DiagnosticFormat::Ghc),
+        "hlint-json:".value(DiagnosticFormat::HlintJson),
+    ))
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::PositionRange;
+    use crate::ghci::parse::Severity;
+
+    fn output(stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(0),
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(
+            diagnostic_format_prefix
+                .parse_peek("ghc-diagnostics:my-linter")
+                .unwrap(),
+            ("my-linter", DiagnosticFormat::Ghc)
+        );
+        assert_eq!(
+            diagnostic_format_prefix
+                .parse_peek("hlint-json:hlint --json src")
+                .unwrap(),
+            ("hlint --json src", DiagnosticFormat::HlintJson)
+        );
+        assert!(diagnostic_format_prefix.parse_peek("hlint src").is_err());
+    }
+
+    #[test]
+    fn test_parse_ghc_diagnostics() {
+        assert_eq!(
+            DiagnosticFormat::Ghc
+                .parse(&output(
+                    indoc!(
+                        "
+                        Checking 2 files...
+                        src/MyLib.hs:4:11: warning: Don't use `head`
+                        "
+                    ),
+                    indoc!(
+                        "
+                        src/MyModule.hs:1:1: error:
+                            Missing module header
+                        "
+                    ),
+                ))
+                .unwrap(),
+            vec![
+                GhcDiagnostic {
+                    severity: Severity::Warning,
+                    path: Some("src/MyLib.hs".into()),
+                    span: PositionRange::new(4, 11, 4, 11),
+                    message: "Don't use `head`\n".into(),
+                },
+                GhcDiagnostic {
+                    severity: Severity::Error,
+                    path: Some("src/MyModule.hs".into()),
+                    span: PositionRange::new(1, 1, 1, 1),
+                    message: "\n    Missing module header\n".into(),
+                },
+            ]
+        );
+    }
+}
@@ -1,16 +1,27 @@
 use std::collections::VecDeque;
 use std::fmt::Display;
 use std::ops::Range;
+use std::time::Duration;
 
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use eyre::eyre;
 use line_span::LineSpanExt;
+use serde::Serialize;
 use winnow::ascii::line_ending;
 use winnow::ascii::space0;
+use winnow::ascii::till_line_ending;
 use winnow::combinator::alt;
+use winnow::combinator::not;
 use winnow::combinator::opt;
 use winnow::combinator::peek;
+use winnow::combinator::preceded;
 use winnow::combinator::repeat;
 use winnow::combinator::repeat_till;
+use winnow::error::ContextError;
+use winnow::error::ErrMode;
+use winnow::stream::Location;
+use winnow::token::literal;
 use winnow::Located;
 use winnow::PResult;
 use winnow::Parser;
@@ -20,6 +31,15 @@ use crate::ghci::GhciCommand;
 
 use super::lines::rest_of_line;
 use super::lines::until_newline;
+use super::GhcDiagnostic;
+use super::PositionRange;
+use super::Severity;
+
+/// Doctest's marker for a blank line in an example's expected output.
+const BLANKLINE: &str = "<BLANKLINE>";
+
+/// The marker starting a doctest-style example.
+const DOCTEST_MARKER: &str = "-- >>>";
 
 /// A (Haskell) command for `ghciwatch` to evaluate in `ghci`.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,14 +55,221 @@ pub struct EvalCommand {
     column: usize,
     /// The byte offsets corresponding to the span this command is from.
     byte_span: Range<usize>,
+    /// Is this command a doctest-style `-- >>>` example?
+    doctest: bool,
+    /// The marker the command was written with, like `-- $>`, for display.
+    marker: String,
+    /// Where this command's output is written in the file.
+    output: OutputBlock,
+}
+
+/// The output of an [`EvalCommand`] written in the file beneath it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OutputBlock {
+    /// The expected output, if the command should be checked against it.
+    expected: Option<String>,
+    /// The whitespace before the comment marker on each line.
+    indent: String,
+    /// The byte offsets of the lines the output is written on. This is empty if the command
+    /// doesn't have any output written beneath it yet.
+    span: Range<usize>,
+}
+
+impl OutputBlock {
+    /// An empty output block at the given byte offset.
+    fn empty(indent: &str, offset: usize) -> Self {
+        Self {
+            expected: None,
+            indent: indent.to_owned(),
+            span: offset..offset,
+        }
+    }
+}
+
+impl EvalCommand {
+    /// Does this command's output need to be checked against an expected output?
+    pub fn has_expected_output(&self) -> bool {
+        self.output.expected.is_some()
+    }
+
+    /// Check this command's `output` against its expected output, returning a diagnostic for the
+    /// command in the file at `path` if they don't match.
+    ///
+    /// Trailing whitespace is ignored.
+    pub fn check_output(&self, path: &Utf8Path, output: &str) -> Option<GhcDiagnostic> {
+        let expected = normalize_output(self.output.expected.as_ref()?);
+        let output = strip_ansi_escapes::strip_str(output);
+        let actual = normalize_output(&output);
+        if expected == actual {
+            return None;
+        }
+
+        let mut message = format!(
+            "\n    Eval output doesn't match: {}\n",
+            self.display_command
+        );
+        for (heading, lines) in [("Expected", &expected), ("But got", &actual)] {
+            message.push_str(&format!("    {heading}:\n"));
+            for line in lines {
+                message.push_str(&format!("      {line}\n"));
+            }
+        }
+
+        Some(GhcDiagnostic {
+            severity: Severity::Error,
+            path: Some(path.to_owned()),
+            span: PositionRange::new(self.line, self.column, self.line, self.column),
+            message,
+        })
+    }
+
+    /// Get the result of running this command in the file at `path`, given its `output`.
+    pub fn result(&self, path: &Utf8Path, output: &str) -> EvalResult {
+        let output = strip_ansi_escapes::strip_str(output);
+        EvalResult {
+            path: path.to_owned(),
+            line: self.line,
+            column: self.column,
+            command: self.display_command.clone(),
+            status: EvalStatus::from_output(&output),
+            output,
+        }
+    }
+
+    /// Get a diagnostic for this command in the file at `path` timing out after `timeout`.
+    pub fn timeout_diagnostic(&self, path: &Utf8Path, timeout: Duration) -> GhcDiagnostic {
+        GhcDiagnostic {
+            severity: Severity::Error,
+            path: Some(path.to_owned()),
+            span: PositionRange::new(self.line, self.column, self.line, self.column),
+            message: format!(
+                "\n    Eval timed out after {timeout:.2?}: {}\n",
+                self.display_command
+            ),
+        }
+    }
+
+    /// Format `output` to be written beneath this command, in place of its existing output block.
+    ///
+    /// Eval command output is written on lines starting with `output_marker`, like `-- <$`.
+    fn format_output(&self, output: &str, output_marker: &str) -> String {
+        let output = strip_ansi_escapes::strip_str(output);
+        let indent = &self.output.indent;
+        let mut ret = String::new();
+        for line in normalize_output(&output) {
+            match (self.doctest, line.is_empty()) {
+                (true, true) => ret.push_str(&format!("{indent}-- {BLANKLINE}\n")),
+                (true, false) => ret.push_str(&format!("{indent}-- {line}\n")),
+                (false, true) => ret.push_str(&format!("{indent}{output_marker}\n")),
+                (false, false) => ret.push_str(&format!("{indent}{output_marker} {line}\n")),
+            }
+        }
+        // Keep the output block for eval commands, even if there's no output.
+        if ret.is_empty() && !self.doctest {
+            ret.push_str(&format!("{indent}{output_marker}\n"));
+        }
+        ret
+    }
+}
+
+/// The result of running an [`EvalCommand`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EvalResult {
+    /// The file the command is from.
+    pub path: Utf8PathBuf,
+    /// The line number the command is from.
+    pub line: usize,
+    /// The column number the command is from.
+    pub column: usize,
+    /// The command, without `:{` and `:}` for multiline commands.
+    pub command: String,
+    /// The command's output.
+    pub output: String,
+    /// Whether the command succeeded.
+    pub status: EvalStatus,
+}
+
+/// Whether an [`EvalCommand`] succeeded, according to its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvalStatus {
+    /// The command ran without errors.
+    Ok,
+    /// The command threw an exception, like `*** Exception: Prelude.head: empty list`.
+    Exception,
+    /// The command failed to compile, like `<interactive>:1:1: error: ...`.
+    Error,
+    /// The command was interrupted because it took too long.
+    Timeout,
+}
+
+impl EvalStatus {
+    /// Determine the status of an eval command from its output.
+    fn from_output(output: &str) -> Self {
+        let mut status = Self::Ok;
+        for line in output.lines() {
+            if line.starts_with("<interactive>:") && line.contains(": error:") {
+                return Self::Error;
+            } else if line.starts_with("*** Exception:") {
+                status = Self::Exception;
+            }
+        }
+        status
+    }
+}
+
+impl Display for EvalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Exception => write!(f, "exception"),
+            Self::Error => write!(f, "error"),
+            Self::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// Write the output of eval commands into a file's `contents`, returning the new contents.
+///
+/// The `outputs` pair commands with their actual outputs, and must be in the order the commands
+/// appear in the file. Each command's existing output block is replaced; commands without one get
+/// a new one beneath them, written with `output_marker`.
+pub fn update_expected_output<'a>(
+    contents: &str,
+    outputs: impl IntoIterator<Item = (&'a EvalCommand, &'a str)>,
+    output_marker: &str,
+) -> String {
+    let mut ret = String::with_capacity(contents.len());
+    let mut last_end = 0;
+    for (command, output) in outputs {
+        let span = &command.output.span;
+        ret.push_str(&contents[last_end..span.start]);
+        // A command on the last line of a file without a trailing newline.
+        if !ret.is_empty() && !ret.ends_with('\n') {
+            ret.push('\n');
+        }
+        ret.push_str(&command.format_output(output, output_marker));
+        last_end = span.end;
+    }
+    ret.push_str(&contents[last_end..]);
+    ret
+}
+
+/// Split output into lines, ignoring trailing whitespace.
+fn normalize_output(output: &str) -> Vec<&str> {
+    let mut lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
 }
 
 impl Display for EvalCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}:{}: -- $> {}",
-            self.line, self.column, self.display_command
+            "{}:{}: {} {}",
+            self.line, self.column, self.marker, self.display_command
         )
     }
 }
@@ -53,11 +280,119 @@ struct ByteSpanCommand {
     command: GhciCommand,
     display_command: String,
     span: Range<usize>,
+    doctest: bool,
+    output: OutputBlock,
+}
+
+/// How eval commands are written in files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalSyntax {
+    /// The marker starting a single-line eval command, like `-- $>`.
+    pub line_marker: String,
+    /// The marker starting a multi-line eval command, like `{- $>`.
+    pub multiline_start_marker: String,
+    /// The marker ending a multi-line eval command, like `<$ -}`.
+    pub multiline_end_marker: String,
+    /// The marker starting each line of an eval command's output, like `-- <$`.
+    pub output_marker: String,
+    /// Parse Haddock `-- >>>` examples as well.
+    pub doctests: bool,
+}
+
+impl Default for EvalSyntax {
+    fn default() -> Self {
+        Self {
+            line_marker: "-- $>".to_owned(),
+            multiline_start_marker: "{- $>".to_owned(),
+            multiline_end_marker: "<$ -}".to_owned(),
+            output_marker: "-- <$".to_owned(),
+            doctests: false,
+        }
+    }
+}
+
+/// Parse the contents of the Haskell source file at `path` into a `Vec` of [`EvalCommand`]s to
+/// evaluate on reloads.
+///
+/// Literate Haskell (`.lhs`) files are parsed with [`parse_literate_eval_commands`].
+pub fn parse_file_eval_commands(
+    path: &Utf8Path,
+    contents: &str,
+    syntax: &EvalSyntax,
+) -> eyre::Result<Vec<EvalCommand>> {
+    if path.extension() == Some("lhs") {
+        parse_literate_eval_commands(contents, syntax)
+    } else {
+        parse_eval_commands(contents, syntax)
+    }
+}
+
+/// Parse literate Haskell source file contents into a `Vec` of [`EvalCommand`]s to evaluate on
+/// reloads.
+///
+/// Both bird-track (`> `) and LaTeX-style (`\begin{code}`) literate files are supported; eval
+/// commands are only parsed from code.
+pub fn parse_literate_eval_commands(
+    contents: &str,
+    syntax: &EvalSyntax,
+) -> eyre::Result<Vec<EvalCommand>> {
+    let (code, bird_tracks) = unlit(contents);
+    let mut commands = parse_eval_commands(&code, syntax)?;
+    if bird_tracks {
+        // Output blocks are indented from the start of the line, so their indent starts with the
+        // bird track we blanked out.
+        for command in &mut commands {
+            command.output.indent.replace_range(..1, ">");
+        }
+    }
+    Ok(commands)
+}
+
+/// Blank out everything except the code in a literate Haskell file, keeping the byte offsets,
+/// line numbers, and column numbers of the code the same.
+///
+/// Returns the blanked contents, and whether the file uses bird tracks. Bird tracks are replaced
+/// with spaces.
+fn unlit(contents: &str) -> (String, bool) {
+    let bird_tracks = !contents
+        .lines()
+        .any(|line| line.trim_end() == "\\begin{code}");
+    let mut ret = String::with_capacity(contents.len());
+    let mut in_code = false;
+    for line in contents.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[text.len()..];
+        let code = if bird_tracks {
+            let code = text.strip_prefix('>');
+            if code.is_some() {
+                ret.push(' ');
+            }
+            code
+        } else {
+            match text.trim_end() {
+                "\\begin{code}" => {
+                    in_code = true;
+                    None
+                }
+                "\\end{code}" => {
+                    in_code = false;
+                    None
+                }
+                _ => in_code.then_some(text),
+            }
+        };
+        match code {
+            Some(code) => ret.push_str(code),
+            None => ret.push_str(&" ".repeat(text.len())),
+        }
+        ret.push_str(ending);
+    }
+    (ret, bird_tracks)
 }
 
 /// Parse Haskell source file contents into a `Vec` of [`EvalCommand`]s to evaluate on reloads.
-pub fn parse_eval_commands(contents: &str) -> eyre::Result<Vec<EvalCommand>> {
-    let mut byte_commands = eval_commands
+pub fn parse_eval_commands(contents: &str, syntax: &EvalSyntax) -> eyre::Result<Vec<EvalCommand>> {
+    let mut byte_commands = eval_commands(syntax)
         .parse(Located::new(contents))
         .map_err(|err| eyre!("{err}"))?;
 
@@ -101,6 +436,13 @@ pub fn parse_eval_commands(contents: &str) -> eyre::Result<Vec<EvalCommand>> {
                 line: line_number,
                 column: column_number,
                 byte_span: byte_command.span,
+                doctest: byte_command.doctest,
+                marker: if byte_command.doctest {
+                    DOCTEST_MARKER.to_owned()
+                } else {
+                    syntax.line_marker.clone()
+                },
+                output: byte_command.output,
             });
 
             // Start working on the next command.
@@ -114,78 +456,207 @@ pub fn parse_eval_commands(contents: &str) -> eyre::Result<Vec<EvalCommand>> {
     Ok(commands)
 }
 
-/// Parse file contents into eval commands.
-fn eval_commands(input: &mut Located<&str>) -> PResult<VecDeque<ByteSpanCommand>> {
+/// Parse file contents into eval commands written with the given `syntax`.
+fn eval_commands(
+    syntax: &EvalSyntax,
+) -> impl FnMut(&mut Located<&str>) -> PResult<VecDeque<ByteSpanCommand>> + '_ {
     enum Item {
         Command(ByteSpanCommand),
         Ignore,
     }
 
-    repeat(
-        0..,
-        alt((
-            line_eval_command.map(Item::Command),
-            multiline_eval_command.map(Item::Command),
-            rest_of_line.map(|_| Item::Ignore),
-        )),
-    )
-    .fold(VecDeque::new, |mut commands, item| {
-        match item {
-            Item::Command(command) => commands.push_back(command),
-            Item::Ignore => {}
+    let doctest_command = move |input: &mut Located<&str>| {
+        if syntax.doctests {
+            doctest_command(syntax, input)
+        } else {
+            Err(ErrMode::Backtrack(ContextError::new()))
         }
-        commands
-    })
-    .parse_next(input)
+    };
+
+    move |input| {
+        repeat(
+            0..,
+            alt((
+                line_eval_command(&syntax.line_marker, &syntax.output_marker).map(Item::Command),
+                multiline_eval_command(
+                    &syntax.multiline_start_marker,
+                    &syntax.multiline_end_marker,
+                    &syntax.output_marker,
+                )
+                .map(Item::Command),
+                doctest_command.map(Item::Command),
+                rest_of_line.map(|_| Item::Ignore),
+            )),
+        )
+        .fold(VecDeque::new, |mut commands, item| {
+            match item {
+                Item::Command(command) => commands.push_back(command),
+                Item::Ignore => {}
+            }
+            commands
+        })
+        .parse_next(input)
+    }
 }
 
-/// Parse a single-line eval command starting with `-- $> `.
+/// Parse a single-line eval command starting with `marker`, like `-- $> `, followed by its output
+/// block written with `output_marker`.
 ///
 /// Unlike `ghcid`, whitespace is allowed before the eval comment.
-fn line_eval_command(input: &mut Located<&str>) -> PResult<ByteSpanCommand> {
+fn line_eval_command<'m>(
+    marker: &'m str,
+    output_marker: &'m str,
+) -> impl FnMut(&mut Located<&str>) -> PResult<ByteSpanCommand> + 'm {
+    move |input| {
+        let indent = space0.parse_next(input)?;
+        let _ = literal(marker).parse_next(input)?;
+        let _ = space0.parse_next(input)?;
+        let (command, span) = until_newline.with_span().parse_next(input)?;
+        let command: GhciCommand = command.to_owned().into();
+        let output = output_block(indent, output_marker, input)?;
+
+        Ok(ByteSpanCommand {
+            display_command: command.clone().into(),
+            command,
+            span,
+            doctest: false,
+            output,
+        })
+    }
+}
+
+/// Parse the output block of an eval command, written on the following lines starting with
+/// `output_marker`, like `-- <$`.
+///
+/// If there's no output block, an empty one is returned, indented with `indent`.
+fn output_block(
+    indent: &str,
+    output_marker: &str,
+    input: &mut Located<&str>,
+) -> PResult<OutputBlock> {
+    let block = opt(|input: &mut _| expected_output(output_marker, input)).parse_next(input)?;
+    Ok(block.unwrap_or_else(|| OutputBlock::empty(indent, input.location())))
+}
+
+/// Parse the expected output of an eval command, written on the following lines starting with
+/// `output_marker`, like `-- <$`.
+fn expected_output(output_marker: &str, input: &mut Located<&str>) -> PResult<OutputBlock> {
+    let indent = peek(space0).parse_next(input)?;
+    let (lines, span): (Vec<&str>, _) = repeat(1.., |input: &mut _| {
+        expected_output_line(output_marker, input)
+    })
+    .with_span()
+    .parse_next(input)?;
+
+    Ok(OutputBlock {
+        expected: Some(lines.into_iter().map(|line| format!("{line}\n")).collect()),
+        indent: indent.to_owned(),
+        span,
+    })
+}
+
+/// Parse a line of expected output for an eval command, like `-- <$ 55`.
+fn expected_output_line<'i>(output_marker: &str, input: &mut Located<&'i str>) -> PResult<&'i str> {
     let _ = space0.parse_next(input)?;
-    // TODO: Perhaps these eval markers should be customizable?
-    let _ = "-- $>".parse_next(input)?;
+    let _ = literal(output_marker).parse_next(input)?;
+    // Make sure this isn't a comment like `-- <$> is fmap`.
+    let line = alt((
+        preceded(' ', till_line_ending),
+        peek(line_ending_or_eof).map(|_| ""),
+    ))
+    .parse_next(input)?;
+    let _ = line_ending_or_eof.parse_next(input)?;
+    Ok(line)
+}
+
+/// Parse a doctest-style example starting with `-- >>> `, followed by its expected output.
+///
+/// The expected output is the following comment lines, up to the next example, a blank comment
+/// line, a Haddock comment like `-- |` or `-- ^`, or an eval command written with `syntax`. Like
+/// doctest, `<BLANKLINE>` stands for a blank line in the expected output.
+fn doctest_command(syntax: &EvalSyntax, input: &mut Located<&str>) -> PResult<ByteSpanCommand> {
+    let indent = space0.parse_next(input)?;
+    let _ = literal(DOCTEST_MARKER).parse_next(input)?;
     let _ = space0.parse_next(input)?;
     let (command, span) = until_newline.with_span().parse_next(input)?;
     let command: GhciCommand = command.to_owned().into();
 
+    let (lines, expected_span): (Vec<&str>, _) =
+        repeat(0.., |input: &mut _| doctest_output_line(syntax, input))
+            .with_span()
+            .parse_next(input)?;
+    let output = lines
+        .into_iter()
+        .map(|line| if line == BLANKLINE { "" } else { line })
+        .map(|line| format!("{line}\n"))
+        .collect();
+
     Ok(ByteSpanCommand {
         display_command: command.clone().into(),
         command,
         span,
+        doctest: true,
+        output: OutputBlock {
+            expected: Some(output),
+            indent: indent.to_owned(),
+            span: expected_span,
+        },
     })
 }
 
-/// Parse a multi-line eval command starting with `{- $>` and ending with `<$ -}`.
+/// Parse a line of expected output for a doctest-style example, like `-- 55`.
+fn doctest_output_line<'i>(syntax: &EvalSyntax, input: &mut Located<&'i str>) -> PResult<&'i str> {
+    let _ = space0.parse_next(input)?;
+    not(alt((
+        literal(syntax.line_marker.as_str()),
+        literal(syntax.multiline_start_marker.as_str()),
+    )))
+    .parse_next(input)?;
+    let _ = "--".parse_next(input)?;
+    not((space0, alt((">>>", "|", "^")))).parse_next(input)?;
+    let _ = opt(' ').parse_next(input)?;
+    until_newline
+        .verify(|line: &str| !line.trim().is_empty())
+        .parse_next(input)
+}
+
+/// Parse a multi-line eval command starting with `start_marker` and ending with `end_marker`,
+/// like `{- $>` and `<$ -}`, followed by its output block written with `output_marker`.
 ///
 /// Unlike `ghcid`, whitespace is allowed before the eval comment.
-fn multiline_eval_command(input: &mut Located<&str>) -> PResult<ByteSpanCommand> {
-    let _ = space0.parse_next(input)?;
-    let _ = "{- $>".parse_next(input)?;
-    // Parse whitespace after the start marker and don't include it in the output command.
-    let _ = space0.parse_next(input)?;
-    // Ditto for a line ending after the start marker.
-    let _ = opt(line_ending).parse_next(input)?;
+fn multiline_eval_command<'m>(
+    start_marker: &'m str,
+    end_marker: &'m str,
+    output_marker: &'m str,
+) -> impl FnMut(&mut Located<&str>) -> PResult<ByteSpanCommand> + 'm {
+    move |input| {
+        let indent = space0.parse_next(input)?;
+        let _ = literal(start_marker).parse_next(input)?;
+        // Parse whitespace after the start marker and don't include it in the output command.
+        let _ = space0.parse_next(input)?;
+        // Ditto for a line ending after the start marker.
+        let _ = opt(line_ending).parse_next(input)?;
 
-    fn multiline_eval_end(input: &mut Located<&str>) -> PResult<()> {
-        (space0, "<$ -}").void().parse_next(input)
-    }
-    let (command, span) =
-        repeat_till::<_, _, (), _, _, _, _>(0.., rest_of_line, peek(multiline_eval_end))
-            .recognize()
-            .with_span()
-            .parse_next(input)?;
-    multiline_eval_end.parse_next(input)?;
-    let _ = (space0, line_ending_or_eof).parse_next(input)?;
+        let multiline_eval_end = || (space0, literal(end_marker)).void();
+        let (command, span) =
+            repeat_till::<_, _, (), _, _, _, _>(0.., rest_of_line, peek(multiline_eval_end()))
+                .recognize()
+                .with_span()
+                .parse_next(input)?;
+        multiline_eval_end().parse_next(input)?;
+        let _ = (space0, line_ending_or_eof).parse_next(input)?;
+        let output = output_block(indent, output_marker, input)?;
 
-    Ok(ByteSpanCommand {
-        // `command` ends with a newline so we put a newline after the `:{` but not before the
-        // `:}`.
-        command: format!(":{{\n{command}:}}").into(),
-        display_command: command.trim().to_owned(),
-        span,
-    })
+        Ok(ByteSpanCommand {
+            // `command` ends with a newline so we put a newline after the `:{` but not before
+            // the `:}`.
+            command: format!(":{{\n{command}:}}").into(),
+            display_command: command.trim().to_owned(),
+            span,
+            doctest: false,
+            output,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -195,47 +666,62 @@ mod tests {
     use indoc::indoc;
     use pretty_assertions::assert_eq;
 
+    fn doctests() -> EvalSyntax {
+        EvalSyntax {
+            doctests: true,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_line_eval_command() {
         assert_eq!(
-            line_eval_command
+            line_eval_command("-- $>", "-- <$")
                 .parse(Located::new("-- $> foo\n"))
                 .unwrap(),
             ByteSpanCommand {
                 command: "foo".to_owned().into(),
                 display_command: "foo".into(),
                 span: 6..10,
+                doctest: false,
+                output: OutputBlock::empty("", 10),
             }
         );
 
         assert_eq!(
-            line_eval_command.parse(Located::new("-- $>foo\n")).unwrap(),
+            line_eval_command("-- $>", "-- <$")
+                .parse(Located::new("-- $>foo\n"))
+                .unwrap(),
             ByteSpanCommand {
                 command: "foo".to_owned().into(),
                 display_command: "foo".into(),
                 span: 5..9,
+                doctest: false,
+                output: OutputBlock::empty("", 9),
             }
         );
 
         // Leading whitespace.
         assert_eq!(
-            line_eval_command
+            line_eval_command("-- $>", "-- <$")
                 .parse(Located::new("   -- $> foo\n"))
                 .unwrap(),
             ByteSpanCommand {
                 command: "foo".to_owned().into(),
                 display_command: "foo".into(),
                 span: 9..13,
+                doctest: false,
+                output: OutputBlock::empty("   ", 13),
             }
         );
 
         // Negative cases.
         // Extra newline at end.
-        assert!(line_eval_command
+        assert!(line_eval_command("-- $>", "-- <$")
             .parse(Located::new("-- $> foo\n\n"))
             .is_err());
         // Multiple commands.
-        assert!(line_eval_command
+        assert!(line_eval_command("-- $>", "-- <$")
             .parse(Located::new(indoc!(
                 "
                 -- $> foo
@@ -248,7 +734,7 @@ mod tests {
     #[test]
     fn test_multiline_eval_command() {
         assert_eq!(
-            multiline_eval_command
+            multiline_eval_command("{- $>", "<$ -}", "-- <$")
                 .parse(Located::new(indoc!(
                     "
                     {- $>
@@ -268,12 +754,14 @@ mod tests {
                 .into(),
                 display_command: "foo".into(),
                 span: 6..10,
+                doctest: false,
+                output: OutputBlock::empty("", 16),
             }
         );
 
         // Multiple lines, command after start marker.
         assert_eq!(
-            multiline_eval_command
+            multiline_eval_command("{- $>", "<$ -}", "-- <$")
                 .parse(Located::new(indoc!(
                     "
                     {- $> puppy
@@ -304,12 +792,14 @@ mod tests {
                 )
                 .into(),
                 span: 6..28,
+                doctest: false,
+                output: OutputBlock::empty("", 34),
             }
         );
 
         // Whitespace before start marker.
         assert_eq!(
-            multiline_eval_command
+            multiline_eval_command("{- $>", "<$ -}", "-- <$")
                 .parse(Located::new(indoc!(
                     "     {- $> puppy
                     <$ -}
@@ -327,11 +817,13 @@ mod tests {
                 .into(),
                 display_command: "puppy".into(),
                 span: 11..17,
+                doctest: false,
+                output: OutputBlock::empty("     ", 23),
             }
         );
 
         assert_eq!(
-            multiline_eval_command
+            multiline_eval_command("{- $>", "<$ -}", "-- <$")
                 .parse(Located::new(indoc!(
                     "   {- $>
                         puppy
@@ -350,12 +842,14 @@ mod tests {
                 .into(),
                 display_command: "puppy".into(),
                 span: 9..19,
+                doctest: false,
+                output: OutputBlock::empty("   ", 25),
             }
         );
 
         // Whitespace before end marker.
         assert_eq!(
-            multiline_eval_command
+            multiline_eval_command("{- $>", "<$ -}", "-- <$")
                 .parse(Located::new(indoc!(
                     "{- $> puppy
                     doggy
@@ -375,12 +869,14 @@ mod tests {
                 .into(),
                 display_command: "puppy\ndoggy".into(),
                 span: 6..18,
+                doctest: false,
+                output: OutputBlock::empty("", 32),
             }
         );
 
         // Negative cases.
         // Markers cannot be on the same line.
-        assert!(multiline_eval_command
+        assert!(multiline_eval_command("{- $>", "<$ -}", "-- <$")
             .parse(Located::new(indoc!(
                 "
                 {- $> puppy <$ -}
@@ -389,7 +885,7 @@ mod tests {
             .is_err());
 
         // Extra newline at end.
-        assert!(multiline_eval_command
+        assert!(multiline_eval_command("{- $>", "<$ -}", "-- <$")
             .parse(Located::new(indoc!(
                 "
                 {- $>
@@ -401,7 +897,7 @@ mod tests {
             .is_err());
 
         // Text after end marker.
-        assert!(multiline_eval_command
+        assert!(multiline_eval_command("{- $>", "<$ -}", "-- <$")
             .parse(Located::new(indoc!(
                 "{- $>
                 doggy
@@ -411,7 +907,7 @@ mod tests {
             .is_err());
 
         // Two commands.
-        assert!(multiline_eval_command
+        assert!(multiline_eval_command("{- $>", "<$ -}", "-- <$")
             .parse(Located::new(indoc!(
                 "
                 {- $>
@@ -421,7 +917,7 @@ mod tests {
                 "
             )))
             .is_err());
-        assert!(multiline_eval_command
+        assert!(multiline_eval_command("{- $>", "<$ -}", "-- <$")
             .parse(Located::new(indoc!(
                 "
                 {- $>
@@ -438,13 +934,16 @@ mod tests {
     #[test]
     fn test_parse_eval_command_no_eol() {
         assert_eq!(
-            parse_eval_commands("-- $> foo").unwrap(),
+            parse_eval_commands("-- $> foo", &EvalSyntax::default()).unwrap(),
             vec![EvalCommand {
                 command: "foo".to_owned().into(),
                 display_command: "foo".to_owned(),
                 line: 1,
                 column: 7,
                 byte_span: 6..9,
+                doctest: false,
+                marker: "-- $>".to_owned(),
+                output: OutputBlock::empty("", 9),
             },]
         )
     }
@@ -452,13 +951,16 @@ mod tests {
     #[test]
     fn test_parse_multiline_eval_command_no_eol() {
         assert_eq!(
-            parse_eval_commands("{- $>\nfoo\n<$ -}").unwrap(),
+            parse_eval_commands("{- $>\nfoo\n<$ -}", &EvalSyntax::default()).unwrap(),
             vec![EvalCommand {
                 command: ":{\nfoo\n:}".to_owned().into(),
                 display_command: "foo".to_owned(),
                 line: 2,
                 column: 1,
                 byte_span: 6..10,
+                doctest: false,
+                marker: "-- $>".to_owned(),
+                output: OutputBlock::empty("", 15),
             },]
         )
     }
@@ -466,8 +968,9 @@ mod tests {
     #[test]
     fn test_parse_eval_commands() {
         assert_eq!(
-            parse_eval_commands(indoc!(
-                r#"
+            parse_eval_commands(
+                indoc!(
+                    r#"
                 module Foo where
 
                 -- $> myFunc 0
@@ -489,7 +992,9 @@ mod tests {
                     <$ -}
                     0
                 "#
-            ))
+                ),
+                &EvalSyntax::default()
+            )
             .unwrap(),
             vec![
                 EvalCommand {
@@ -498,6 +1003,9 @@ mod tests {
                     line: 3,
                     column: 7,
                     byte_span: 24..33,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock::empty("", 33),
                 },
                 EvalCommand {
                     command: ":{\nhello\n:}".to_owned().into(),
@@ -505,6 +1013,9 @@ mod tests {
                     line: 8,
                     column: 1,
                     byte_span: 73..79,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock::empty("", 85),
                 },
                 EvalCommand {
                     command: "goodbye".to_owned().into(),
@@ -512,6 +1023,9 @@ mod tests {
                     line: 10,
                     column: 7,
                     byte_span: 91..99,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock::empty("", 99),
                 },
                 EvalCommand {
                     command: ":{\n    but this does!\n:}".to_owned().into(),
@@ -519,8 +1033,438 @@ mod tests {
                     line: 18,
                     column: 1,
                     byte_span: 190..209,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock::empty("    ", 219),
                 },
             ]
         )
     }
+
+    #[test]
+    fn test_parse_doctests() {
+        let contents = indoc!(
+            r#"
+            module Foo where
+
+            -- | Add one.
+            --
+            -- >>> addOne 1
+            -- 2
+            --
+            -- >>> let x = 1
+            -- >>> putStrLn "a\n\nb"
+            -- a
+            -- <BLANKLINE>
+            -- b
+            addOne :: Int -> Int
+            addOne = (+ 1)
+            "#
+        );
+
+        assert_eq!(
+            parse_eval_commands(contents, &EvalSyntax::default()).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            parse_eval_commands(contents, &doctests()).unwrap(),
+            vec![
+                EvalCommand {
+                    command: "addOne 1".to_owned().into(),
+                    display_command: "addOne 1".into(),
+                    line: 5,
+                    column: 8,
+                    byte_span: 42..51,
+                    doctest: true,
+                    marker: DOCTEST_MARKER.to_owned(),
+                    output: OutputBlock {
+                        expected: Some("2\n".into()),
+                        indent: "".into(),
+                        span: 51..56,
+                    },
+                },
+                EvalCommand {
+                    command: "let x = 1".to_owned().into(),
+                    display_command: "let x = 1".into(),
+                    line: 8,
+                    column: 8,
+                    byte_span: 66..76,
+                    doctest: true,
+                    marker: DOCTEST_MARKER.to_owned(),
+                    output: OutputBlock {
+                        expected: Some("".into()),
+                        indent: "".into(),
+                        span: 76..76,
+                    },
+                },
+                EvalCommand {
+                    command: r#"putStrLn "a\n\nb""#.to_owned().into(),
+                    display_command: r#"putStrLn "a\n\nb""#.into(),
+                    line: 9,
+                    column: 8,
+                    byte_span: 83..101,
+                    doctest: true,
+                    marker: DOCTEST_MARKER.to_owned(),
+                    output: OutputBlock {
+                        expected: Some("a\n\nb\n".into()),
+                        indent: "".into(),
+                        span: 101..126,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_doctest_output_end() {
+        let contents = indoc!(
+            r#"
+            -- >>> addOne 1
+            -- 2
+            -- | Add two.
+            -- >>> addTwo 1
+            -- 3
+            -- ^ The result.
+            -- >>> addThree 1
+            -- 4
+            -- $> addFour 1
+            -- >>> addFive 1
+            -- 6
+            {- $>
+            addSix 1
+            <$ -}
+            "#
+        );
+        let commands = parse_eval_commands(contents, &doctests()).unwrap();
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| (
+                    command.display_command.as_str(),
+                    command.output.expected.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("addOne 1", Some("2\n")),
+                ("addTwo 1", Some("3\n")),
+                ("addThree 1", Some("4\n")),
+                ("addFour 1", None),
+                ("addFive 1", Some("6\n")),
+                ("addSix 1", None),
+            ]
+        );
+
+        let syntax = EvalSyntax {
+            line_marker: "-- eval:".into(),
+            ..doctests()
+        };
+        let commands = parse_eval_commands("-- >>> foo\n-- 1\n-- eval: bar\n", &syntax).unwrap();
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| (
+                    command.display_command.as_str(),
+                    command.output.expected.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            [("foo", Some("1\n")), ("bar", None)]
+        );
+        assert_eq!(
+            commands.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["1:8: -- >>> foo", "3:10: -- eval: bar"]
+        );
+    }
+
+    #[test]
+    fn test_check_output() {
+        let command = parse_eval_commands("-- >>> addOne 1\n-- 2\n", &doctests())
+            .unwrap()
+            .remove(0);
+        assert_eq!(command.check_output("src/Foo.hs".into(), "2  \n\n"), None);
+        assert_eq!(
+            command
+                .check_output("src/Foo.hs".into(), "3\n")
+                .unwrap()
+                .to_string(),
+            indoc!(
+                "
+                src/Foo.hs:1:8: error:
+                    Eval output doesn't match: addOne 1
+                    Expected:
+                      2
+                    But got:
+                      3
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_expected_output() {
+        assert_eq!(
+            parse_eval_commands(
+                indoc!(
+                    "
+                    -- $> addOne 1
+                    -- <$ 2
+                    -- <$
+                    -- <$> is fmap
+                    {- $>
+                    addOne 2
+                    <$ -}
+                      -- <$ 3
+                    "
+                ),
+                &EvalSyntax::default()
+            )
+            .unwrap(),
+            vec![
+                EvalCommand {
+                    command: "addOne 1".to_owned().into(),
+                    display_command: "addOne 1".into(),
+                    line: 1,
+                    column: 7,
+                    byte_span: 6..15,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock {
+                        expected: Some("2\n\n".into()),
+                        indent: "".into(),
+                        span: 15..29,
+                    },
+                },
+                EvalCommand {
+                    command: ":{\naddOne 2\n:}".to_owned().into(),
+                    display_command: "addOne 2".into(),
+                    line: 6,
+                    column: 1,
+                    byte_span: 50..59,
+                    doctest: false,
+                    marker: "-- $>".to_owned(),
+                    output: OutputBlock {
+                        expected: Some("3\n".into()),
+                        indent: "  ".into(),
+                        span: 65..75,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_custom_markers() {
+        let syntax = EvalSyntax {
+            line_marker: "-- >>>".into(),
+            multiline_start_marker: "{- >>>".into(),
+            multiline_end_marker: "-}".into(),
+            output_marker: "-- =>".into(),
+            doctests: false,
+        };
+        let contents = indoc!(
+            "
+            -- >>> foo
+            -- => 1
+            -- $> bar
+            {- >>>
+            baz
+            -}
+            -- <$ 2
+            "
+        );
+        let commands = parse_eval_commands(contents, &syntax).unwrap();
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| (
+                    command.display_command.as_str(),
+                    command.output.expected.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            [("foo", Some("1\n")), ("baz", None)]
+        );
+        assert_eq!(
+            update_expected_output(contents, commands.iter().zip(["2\n", "3\n"]), "-- =>"),
+            indoc!(
+                "
+                -- >>> foo
+                -- => 2
+                -- $> bar
+                {- >>>
+                baz
+                -}
+                -- => 3
+                -- <$ 2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_literate_bird_tracks() {
+        let contents = indoc!(
+            "
+            Some prose.
+            -- $> prose
+
+            > module Foo where
+            >
+            > -- $> addOne 1
+            > -- <$ 2
+            > addOne :: Int -> Int
+            > addOne = (+ 1)
+            "
+        );
+        let commands = parse_literate_eval_commands(contents, &EvalSyntax::default()).unwrap();
+        assert_eq!(
+            commands,
+            vec![EvalCommand {
+                command: "addOne 1".to_owned().into(),
+                display_command: "addOne 1".into(),
+                line: 6,
+                column: 9,
+                byte_span: 54..63,
+                doctest: false,
+                marker: "-- $>".to_owned(),
+                output: OutputBlock {
+                    expected: Some("2\n".into()),
+                    indent: "> ".into(),
+                    span: 63..73,
+                },
+            }]
+        );
+        assert_eq!(
+            update_expected_output(contents, commands.iter().zip(["3\n"]), "-- <$"),
+            contents.replace("> -- <$ 2", "> -- <$ 3")
+        );
+    }
+
+    #[test]
+    fn test_parse_literate_latex() {
+        let contents = indoc!(
+            r"
+            Some prose.
+            -- $> prose
+
+            \begin{code}
+            module Foo where
+
+            -- $> addOne 1
+            addOne :: Int -> Int
+            addOne = (+ 1)
+            \end{code}
+
+            {- $>
+            more prose
+            <$ -}
+            "
+        );
+        let commands = parse_literate_eval_commands(contents, &EvalSyntax::default()).unwrap();
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| (command.line, command.display_command.as_str()))
+                .collect::<Vec<_>>(),
+            [(7, "addOne 1")]
+        );
+        assert_eq!(
+            update_expected_output(contents, commands.iter().zip(["2\n"]), "-- <$"),
+            contents.replace("-- $> addOne 1\n", "-- $> addOne 1\n-- <$ 2\n")
+        );
+    }
+
+    #[test]
+    fn test_result() {
+        let commands = parse_eval_commands("-- $> head []\n", &EvalSyntax::default()).unwrap();
+        let path = Utf8Path::new("src/MyLib.hs");
+        assert_eq!(
+            commands[0].result(path, "*** Exception: Prelude.head: empty list\n"),
+            EvalResult {
+                path: path.to_owned(),
+                line: 1,
+                column: 7,
+                command: "head []".into(),
+                output: "*** Exception: Prelude.head: empty list\n".into(),
+                status: EvalStatus::Exception,
+            }
+        );
+        assert_eq!(
+            commands[0]
+                .result(
+                    path,
+                    indoc!(
+                        "
+                        <interactive>:1:1: error: [GHC-39999]
+                            • No instance for ‘Show a0’ arising from a use of ‘print’
+                        "
+                    )
+                )
+                .status,
+            EvalStatus::Error
+        );
+        assert_eq!(commands[0].result(path, "1\n").status, EvalStatus::Ok);
+    }
+
+    #[test]
+    fn test_timeout_diagnostic() {
+        let commands =
+            parse_eval_commands("\n  -- $> forever (pure ())\n", &EvalSyntax::default()).unwrap();
+        assert_eq!(
+            commands[0]
+                .timeout_diagnostic(Utf8Path::new("src/MyLib.hs"), Duration::from_secs(5))
+                .to_string(),
+            indoc!(
+                "
+                src/MyLib.hs:2:9: error:
+                    Eval timed out after 5.00s: forever (pure ())
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_update_expected_output() {
+        let contents = indoc!(
+            "
+            -- $> addOne 1
+            -- <$ 3
+            -- $> print ()
+            -- <$ ()
+              -- >>> putStrLn \"a\\n\\nb\"
+            -- >>> addOne 2
+            -- 4
+
+            -- $> print ()
+            "
+        );
+        let commands = parse_eval_commands(contents, &doctests()).unwrap();
+        let outputs = ["2\n", "()\n", "a\n\nb\n", "3\n", "()\n"];
+        assert_eq!(
+            update_expected_output(contents, commands.iter().zip(outputs), "-- <$"),
+            indoc!(
+                "
+                -- $> addOne 1
+                -- <$ 2
+                -- $> print ()
+                -- <$ ()
+                  -- >>> putStrLn \"a\\n\\nb\"
+                  -- a
+                  -- <BLANKLINE>
+                  -- b
+                -- >>> addOne 2
+                -- 3
+
+                -- $> print ()
+                -- <$ ()
+                "
+            )
+        );
+
+        // New output blocks are indented like their commands, even at the end of the file.
+        let contents = "  -- $> print ()";
+        let commands = parse_eval_commands(contents, &EvalSyntax::default()).unwrap();
+        assert_eq!(
+            update_expected_output(contents, commands.iter().zip(["()\n"]), "-- <$"),
+            "  -- $> print ()\n  -- <$ ()\n"
+        );
+    }
 }
@@ -10,6 +10,7 @@ use winnow::combinator::repeat;
 use winnow::prelude::*;
 
 mod position;
+pub use position::parse_position_range;
 pub use position::PositionRange;
 
 mod severity;
@@ -18,7 +19,7 @@ pub use severity::Severity;
 mod single_quote;
 
 mod path_colon;
-use path_colon::path_colon;
+pub use path_colon::path_colon;
 
 mod compiling;
 pub use compiling::compiling;
@@ -92,7 +93,6 @@ pub enum GhcMessage {
 
 impl GhcMessage {
     /// Extract the contained diagnostic, if any.
-    #[cfg(test)]
     pub fn into_diagnostic(self) -> Option<GhcDiagnostic> {
         match self {
             GhcMessage::Diagnostic(diagnostic) => Some(diagnostic),
@@ -18,7 +18,6 @@ pub struct Position {
 
 impl Position {
     /// Construct a new [`Position`] from a line and column number.
-    #[cfg(test)]
     pub fn new(line: usize, column: usize) -> Self {
         Self { line, column }
     }
@@ -46,7 +45,6 @@ pub struct PositionRange {
 
 impl PositionRange {
     /// Construct a new span from the given lines and columns.
-    #[cfg(test)]
     pub fn new(start_line: usize, start_column: usize, end_line: usize, end_column: usize) -> Self {
         Self {
             start: Position::new(start_line, start_column),
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+use eyre::WrapErr;
+use serde::Deserialize;
+
+use super::GhcDiagnostic;
+use super::PositionRange;
+use super::Severity;
+
+/// A single hint from `hlint --json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hint {
+    severity: String,
+    hint: String,
+    file: String,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    from: String,
+    to: Option<String>,
+    #[serde(default)]
+    note: Vec<String>,
+}
+
+impl Hint {
+    fn into_diagnostic(self) -> Option<GhcDiagnostic> {
+        let severity = match self.severity.as_str() {
+            "Ignore" => return None,
+            "Error" => Severity::Error,
+            _ => Severity::Warning,
+        };
+
+        // Format the hint like GHC does, with the details indented on the following lines, so
+        // that `ghcid` plugins can tell where each diagnostic ends.
+        let mut message = format!("[hlint {}] {}\n", self.severity, self.hint);
+        write_section(&mut message, "Found", &self.from);
+        if let Some(to) = &self.to {
+            write_section(&mut message, "Perhaps", to);
+        }
+        for note in &self.note {
+            writeln!(message, "    Note: {note}").expect("Writing to a `String` never fails");
+        }
+
+        Some(GhcDiagnostic {
+            severity,
+            path: Some(self.file.into()),
+            span: PositionRange::new(
+                self.start_line,
+                self.start_column,
+                self.end_line,
+                self.end_column,
+            ),
+            message,
+        })
+    }
+}
+
+fn write_section(message: &mut String, heading: &str, code: &str) {
+    writeln!(message, "    {heading}:").expect("Writing to a `String` never fails");
+    for line in code.lines() {
+        writeln!(message, "      {line}").expect("Writing to a `String` never fails");
+    }
+}
+
+/// Parse the output of `hlint --json` into diagnostics.
+///
+/// Hints with the `Ignore` severity are skipped, and `Error` hints are reported as errors; all
+/// other hints are reported as warnings.
+pub fn parse_hlint_json(output: &str) -> eyre::Result<Vec<GhcDiagnostic>> {
+    let hints: Vec<Hint> =
+        serde_json::from_str(output).wrap_err("Failed to parse `hlint --json` output")?;
+    Ok(hints
+        .into_iter()
+        .filter_map(Hint::into_diagnostic)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_hlint_json() {
+        assert_eq!(
+            parse_hlint_json(indoc!(
+                r#"
+                [{"module":["MyLib"],"decl":["example"],"severity":"Suggestion","hint":"Use concatMap","file":"src/MyLib.hs","startLine":4,"startColumn":11,"endLine":4,"endColumn":28,"from":"concat (map f xs)","to":"concatMap f xs","note":[],"refactorings":"[]"}
+                ,{"module":["MyLib"],"decl":["other"],"severity":"Warning","hint":"Redundant bracket","file":"src/MyLib.hs","startLine":7,"startColumn":9,"endLine":8,"endColumn":3,"from":"(f\n  x)","to":null,"note":["may change evaluation"],"refactorings":"[]"}
+                ,{"module":["MyLib"],"decl":["ignored"],"severity":"Ignore","hint":"Eta reduce","file":"src/MyLib.hs","startLine":9,"startColumn":1,"endLine":9,"endColumn":10,"from":"f x = g x","to":"f = g","note":[],"refactorings":"[]"}]
+                "#
+            ))
+            .unwrap(),
+            vec![
+                GhcDiagnostic {
+                    severity: Severity::Warning,
+                    path: Some("src/MyLib.hs".into()),
+                    span: PositionRange::new(4, 11, 4, 28),
+                    message: indoc!(
+                        "
+                        [hlint Suggestion] Use concatMap
+                            Found:
+                              concat (map f xs)
+                            Perhaps:
+                              concatMap f xs
+                        "
+                    )
+                    .into(),
+                },
+                GhcDiagnostic {
+                    severity: Severity::Warning,
+                    path: Some("src/MyLib.hs".into()),
+                    span: PositionRange::new(7, 9, 8, 3),
+                    message: indoc!(
+                        "
+                        [hlint Warning] Redundant bracket
+                            Found:
+                              (f
+                                x)
+                            Note: may change evaluation
+                        "
+                    )
+                    .into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hlint_json_empty() {
+        assert_eq!(parse_hlint_json("[]\n").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_hlint_json_invalid() {
+        assert!(parse_hlint_json("No hints\n").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+
+use winnow::ascii::space0;
+use winnow::ascii::space1;
+use winnow::combinator::alt;
+use winnow::combinator::opt;
+use winnow::combinator::repeat;
+use winnow::token::take_till;
+use winnow::PResult;
+use winnow::Parser;
+
+use super::module_name;
+
+/// Parse the names of the modules imported by a Haskell module.
+///
+/// This only looks for `import` declarations at the start of a line, and doesn't know about
+/// comments or CPP, so it may find extra imports. That's fine for deciding which modules might
+/// be affected by a change.
+pub fn parse_imports(contents: &str) -> BTreeSet<String> {
+    contents
+        .lines()
+        .filter_map(|mut line| import.parse_next(&mut line).ok())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Parse an import declaration, like `import qualified Data.Map as Map`, returning the imported
+/// module's name.
+fn import<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    let _ = ("import", space1).parse_next(input)?;
+    let _ = opt(("{-#", space0, "SOURCE", space0, "#-}", space1)).parse_next(input)?;
+    repeat::<_, _, (), _, _>(0.., (alt(("safe", "qualified")), space1)).parse_next(input)?;
+    // `PackageImports`.
+    let _ = opt(('"', take_till(0.., '"'), '"', space1)).parse_next(input)?;
+    module_name.parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_imports() {
+        assert_eq!(
+            parse_imports(indoc!(
+                r#"
+                {-# LANGUAGE PackageImports #-}
+                module MyLibSpec (spec) where
+
+                import Test.Hspec
+                import qualified Data.Map as Map
+                import Data.Text qualified as Text
+                import safe "base" Data.List (sort)
+                import {-# SOURCE #-} MyLib.Types
+                import MyLib
+
+                -- import Commented.Out
+                important :: Int
+                important = 1
+                "#
+            )),
+            [
+                "Data.List",
+                "Data.Map",
+                "Data.Text",
+                "MyLib",
+                "MyLib.Types",
+                "Test.Hspec"
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect()
+        );
+    }
+}
@@ -1,19 +1,32 @@
 //! Parsers for `ghci` output and Haskell code.
 
+mod dependent_files;
+mod diagnostic_format;
 mod eval;
 mod ghc_message;
 mod haskell_grammar;
+mod hlint;
+mod imports;
 mod lines;
 mod module_and_files;
 mod show_paths;
 mod show_targets;
+mod test_output;
 
 use haskell_grammar::module_name;
 use lines::rest_of_line;
 use module_and_files::module_and_files;
 
-pub use eval::parse_eval_commands;
+pub use dependent_files::parse_dependent_files;
+pub use dependent_files::DependentFileBase;
+pub use diagnostic_format::diagnostic_format_prefix;
+pub use diagnostic_format::DiagnosticFormat;
+pub use eval::parse_file_eval_commands;
+pub use eval::update_expected_output;
 pub use eval::EvalCommand;
+pub use eval::EvalResult;
+pub use eval::EvalStatus;
+pub use eval::EvalSyntax;
 pub use ghc_message::compiling;
 pub use ghc_message::parse_ghc_messages;
 pub use ghc_message::CompilationResult;
@@ -22,7 +35,13 @@ pub use ghc_message::CompilingProgress;
 pub use ghc_message::GhcDiagnostic;
 pub use ghc_message::GhcMessage;
 pub use ghc_message::ModulesLoaded;
+pub use ghc_message::PositionRange;
 pub use ghc_message::Severity;
+pub use hlint::parse_hlint_json;
+pub use imports::parse_imports;
 pub use show_paths::parse_show_paths;
 pub use show_paths::ShowPaths;
 pub use show_targets::parse_show_targets;
+pub use test_output::parse_test_results;
+pub use test_output::TestFailure;
+pub use test_output::TestResults;
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::collections::HashSet;
 
 use camino::Utf8Path;
@@ -91,8 +92,86 @@ impl ShowPaths {
 
         Err(eyre!("Couldn't convert {path} to module name"))
     }
+
+    /// Get the paths to watch for changes: the search paths which exist (excluding build
+    /// directories and search paths nested in other search paths), and the `.cabal` and
+    /// `package.yaml` files of the packages they belong to.
+    pub fn watch_paths(&self) -> BTreeSet<Utf8PathBuf> {
+        let dirs = self
+            .absolute_search_paths()
+            .filter(|path| {
+                !path.components().any(|component| {
+                    BUILD_DIRECTORIES
+                        .iter()
+                        .any(|build_dir| component.as_str() == *build_dir)
+                }) && path.is_dir()
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut ret = dirs
+            .iter()
+            .filter(|path| !path.ancestors().skip(1).any(|dir| dirs.contains(dir)))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        for dir in &dirs {
+            for package_file in self.package_files(dir) {
+                if !package_file
+                    .ancestors()
+                    .skip(1)
+                    .any(|dir| ret.contains(dir))
+                {
+                    ret.insert(package_file);
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Find the `.cabal` and `package.yaml` files of the package a search path belongs to, by
+    /// looking in the search path and its parents, up to the working directory or the root of the
+    /// project (a directory containing `cabal.project`, `stack.yaml`, or `.git`).
+    fn package_files(&self, search_path: &Utf8Path) -> Vec<Utf8PathBuf> {
+        for dir in search_path.ancestors() {
+            let package_files = match dir.read_dir_utf8() {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.into_path())
+                    .filter(|path| {
+                        path.extension() == Some("cabal")
+                            || path.file_name() == Some("package.yaml")
+                    })
+                    .filter(|path| path.is_file())
+                    .collect::<Vec<_>>(),
+                Err(err) => {
+                    tracing::debug!(%dir, "Failed to read directory: {err}");
+                    Vec::new()
+                }
+            };
+
+            if !package_files.is_empty() || dir == self.cwd || is_project_root(dir) {
+                return package_files;
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+/// Does the given directory contain a `cabal.project`, `stack.yaml`, or `.git`?
+fn is_project_root(dir: &Utf8Path) -> bool {
+    PROJECT_ROOT_FILES
+        .iter()
+        .any(|name| dir.join(name).exists())
 }
 
+/// Files marking the root of a project, above which we don't look for package files.
+const PROJECT_ROOT_FILES: [&str; 3] = ["cabal.project", "stack.yaml", ".git"];
+
+/// Build directories, which contain generated search paths that shouldn't be watched.
+const BUILD_DIRECTORIES: [&str; 2] = ["dist-newstyle", ".stack-work"];
+
 /// Parse `:show paths` output into a set of module search paths.
 pub fn parse_show_paths(input: &str) -> eyre::Result<ShowPaths> {
     let mut show_paths = show_paths.parse(input).map_err(|err| eyre!("{err}"))?;
@@ -153,6 +232,47 @@ mod tests {
     use indoc::indoc;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn test_watch_paths() {
+        let cwd = Utf8Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/simple");
+        let show_paths = ShowPaths {
+            search_paths: vec![
+                cwd.join("dist-newstyle/build/x86_64-linux/ghc-9.6.6/my-simple-package-0.1.0.0/build/autogen"),
+                cwd.join("src"),
+                cwd.join("test"),
+                cwd.join("src/MyLib"),
+            ],
+            cwd: cwd.clone(),
+        };
+
+        assert_eq!(
+            show_paths.watch_paths(),
+            [cwd.join("my-simple-package.cabal"), cwd.join("src")]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_watch_paths_outside_cwd() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::try_from(tempdir.path().to_owned()).unwrap();
+        std::fs::write(root.join("outer.cabal"), "").unwrap();
+        std::fs::create_dir_all(root.join("project/.git")).unwrap();
+        std::fs::create_dir_all(root.join("project/app")).unwrap();
+        std::fs::create_dir_all(root.join("project/lib/src")).unwrap();
+        let show_paths = ShowPaths {
+            search_paths: vec![root.join("project/lib/src")],
+            cwd: root.join("project/app"),
+        };
+
+        // We don't look for package files above the project root.
+        assert_eq!(
+            show_paths.watch_paths(),
+            [root.join("project/lib/src")].into_iter().collect()
+        );
+    }
+
     #[test]
     fn test_parse_show_paths() {
         assert_eq!(
@@ -0,0 +1,502 @@
+//! Parser for test runner output from `hspec` and `tasty`.
+
+use std::fmt::Display;
+
+use camino::Utf8PathBuf;
+use winnow::ascii::digit1;
+use winnow::combinator::alt;
+use winnow::combinator::opt;
+use winnow::combinator::preceded;
+use winnow::combinator::terminated;
+use winnow::PResult;
+use winnow::Parser;
+
+use super::ghc_message::parse_position_range;
+use super::ghc_message::path_colon;
+use super::GhcDiagnostic;
+use super::PositionRange;
+use super::Severity;
+
+/// A test framework whose output we can parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFramework {
+    /// [`hspec`](https://hspec.github.io/).
+    Hspec,
+    /// [`tasty`](https://github.com/UnkindPartition/tasty).
+    Tasty,
+}
+
+impl Display for TestFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestFramework::Hspec => write!(f, "hspec"),
+            TestFramework::Tasty => write!(f, "tasty"),
+        }
+    }
+}
+
+/// The results of running a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResults {
+    /// The test framework which produced the output.
+    pub framework: TestFramework,
+    /// The number of tests which passed.
+    pub passed: usize,
+    /// The number of tests which failed.
+    pub failed: usize,
+    /// The number of pending tests.
+    pub pending: usize,
+    /// Details about each failing test, when the output includes them.
+    pub failures: Vec<TestFailure>,
+}
+
+impl Display for TestResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed, self.failed)?;
+        if self.pending > 0 {
+            write!(f, ", {} pending", self.pending)?;
+        }
+        Ok(())
+    }
+}
+
+/// A failing test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFailure {
+    /// The test's name, like `Foo.bar does the thing`.
+    pub name: String,
+    /// The file containing the failing assertion, if known.
+    pub path: Option<Utf8PathBuf>,
+    /// The location of the failing assertion.
+    pub span: PositionRange,
+    /// The failure message.
+    pub message: String,
+}
+
+impl TestFailure {
+    /// Convert this failure into an error diagnostic for the error log.
+    pub fn to_diagnostic(&self) -> GhcDiagnostic {
+        // Indent the details so that `ghcid` plugins can tell where each diagnostic ends.
+        let mut message = format!("\n    Test failed: {}\n", self.name);
+        for line in self.message.lines() {
+            if line.is_empty() {
+                message.push('\n');
+            } else {
+                message.push_str("    ");
+                message.push_str(line);
+                message.push('\n');
+            }
+        }
+
+        GhcDiagnostic {
+            severity: Severity::Error,
+            path: self.path.clone(),
+            span: self.span,
+            message,
+        }
+    }
+}
+
+/// Parse test results from `hspec` or `tasty` output, if the output contains any.
+pub fn parse_test_results(output: &str) -> Option<TestResults> {
+    let output = strip_ansi_escapes::strip_str(output);
+    let lines = output.lines().collect::<Vec<_>>();
+    parse_hspec(&lines).or_else(|| parse_tasty(&lines))
+}
+
+/// Parse a `hspec` summary line, like `3 examples, 1 failure, 1 pending`.
+fn hspec_summary(input: &mut &str) -> PResult<(usize, usize, usize)> {
+    let examples =
+        terminated(digit1.parse_to(), alt((" examples", " example"))).parse_next(input)?;
+    let failures = preceded(
+        ", ",
+        terminated(digit1.parse_to(), alt((" failures", " failure"))),
+    )
+    .parse_next(input)?;
+    let pending = opt(preceded(", ", terminated(digit1.parse_to(), " pending")))
+        .parse_next(input)?
+        .unwrap_or(0);
+    Ok((examples, failures, pending))
+}
+
+/// Parse a `tasty` summary line, like `All 3 tests passed (0.01s)` or `1 out of 3 tests failed
+/// (0.01s)`, returning the passed and failed counts.
+fn tasty_summary(input: &mut &str) -> PResult<(usize, usize)> {
+    let (passed, failed) = alt((
+        preceded("All ", terminated(digit1.parse_to(), " tests passed"))
+            .map(|passed: usize| (passed, 0)),
+        (
+            terminated(digit1.parse_to(), " out of "),
+            terminated(digit1.parse_to(), " tests failed"),
+        )
+            .map(|(failed, total): (usize, usize)| (total.saturating_sub(failed), failed)),
+    ))
+    .parse_next(input)?;
+    let _ = opt(preceded(" (", winnow::token::take_until(0.., ")"))).parse_next(input)?;
+    let _ = opt(")").parse_next(input)?;
+    Ok((passed, failed))
+}
+
+/// Parse a source location in a test failure, like `test/FooSpec.hs:10:5:` (`hspec`) or
+/// `test/FooTest.hs:12:` (`tasty` with HUnit).
+fn parse_location(line: &str) -> Option<(Utf8PathBuf, PositionRange)> {
+    let line_only =
+        terminated(digit1.parse_to(), ":").map(|line: usize| PositionRange::new(line, 1, line, 1));
+
+    (path_colon, alt((parse_position_range, line_only)))
+        .parse(line.trim())
+        .ok()
+        .filter(|(path, _)| path.extension().is_some())
+        .map(|(path, span)| (path.to_owned(), span))
+}
+
+/// Remove the common leading whitespace from the given lines and join them, trimming blank lines
+/// from the start and end.
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_owned()
+}
+
+fn parse_hspec(lines: &[&str]) -> Option<TestResults> {
+    let (examples, failed, pending) = lines
+        .iter()
+        .rev()
+        .find_map(|line| hspec_summary.parse(line.trim()).ok())?;
+
+    let mut failures = Vec::new();
+    if let Some(start) = lines.iter().position(|line| *line == "Failures:") {
+        // Failures look like this, with an optional location line:
+        //
+        //   test/FooSpec.hs:10:5:
+        //   1) Foo.bar does the thing
+        //        expected: 1
+        //         but got: 2
+        //
+        //   To rerun use: --match "/Foo/bar/does the thing/"
+        let mut location = None;
+        let mut current: Option<HspecFailure> = None;
+
+        for line in &lines[start + 1..] {
+            let trimmed = line.trim();
+            if trimmed.starts_with("Randomized with seed") || trimmed.starts_with("Finished in") {
+                break;
+            } else if trimmed.starts_with("To rerun use:") {
+                failures.extend(current.take().map(HspecFailure::into_failure));
+            } else if let Some(name) = hspec_failure_header(trimmed) {
+                failures.extend(current.take().map(HspecFailure::into_failure));
+                current = Some(HspecFailure {
+                    name: name.to_owned(),
+                    location: location.take(),
+                    message: Vec::new(),
+                });
+            } else if let Some(current) = &mut current {
+                current.message.push(*line);
+            } else if let Some(parsed) = parse_location(trimmed) {
+                location = Some(parsed);
+            }
+        }
+        failures.extend(current.take().map(HspecFailure::into_failure));
+    }
+
+    Some(TestResults {
+        framework: TestFramework::Hspec,
+        passed: examples.saturating_sub(failed + pending),
+        failed,
+        pending,
+        failures,
+    })
+}
+
+/// A `hspec` failure being parsed.
+struct HspecFailure<'a> {
+    name: String,
+    location: Option<(Utf8PathBuf, PositionRange)>,
+    message: Vec<&'a str>,
+}
+
+impl HspecFailure<'_> {
+    fn into_failure(self) -> TestFailure {
+        let (path, span) = match self.location {
+            Some((path, span)) => (Some(path), span),
+            None => (None, PositionRange::default()),
+        };
+        TestFailure {
+            name: self.name,
+            path,
+            span,
+            message: dedent(&self.message),
+        }
+    }
+}
+
+/// Parse a `hspec` failure header like `1) Foo.bar does the thing`, returning the test name.
+fn hspec_failure_header(line: &str) -> Option<&str> {
+    let (number, name) = line.split_once(") ")?;
+    if !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn parse_tasty(lines: &[&str]) -> Option<TestResults> {
+    let (passed, failed) = lines
+        .iter()
+        .rev()
+        .find_map(|line| tasty_summary.parse(line.trim()).ok())?;
+
+    // Tasty prints a tree of test groups and tests, like this:
+    //
+    //   Tests
+    //     Foo
+    //       bar: FAIL
+    //         test/FooTest.hs:12:
+    //         expected: 1
+    //          but got: 2
+    //         Use -p '/bar/' to rerun this test only.
+    let mut failures = Vec::new();
+    // The enclosing test groups, with their indentation.
+    let mut groups: Vec<(usize, &str)> = Vec::new();
+    let mut lines = lines.iter().peekable();
+    while let Some(line) = lines.next() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        while groups
+            .last()
+            .is_some_and(|(group_indent, _)| *group_indent >= indent)
+        {
+            groups.pop();
+        }
+
+        let Some((name, result)) = trimmed.rsplit_once(':') else {
+            groups.push((indent, trimmed));
+            continue;
+        };
+        let result = result.trim();
+        if result.starts_with("OK") {
+            continue;
+        } else if !result.starts_with("FAIL") {
+            groups.push((indent, trimmed));
+            continue;
+        }
+
+        let mut message = Vec::new();
+        while let Some(next) = lines.peek() {
+            let next_indent = next.len() - next.trim_start().len();
+            if !next.trim().is_empty() && next_indent <= indent {
+                break;
+            }
+            let next = lines.next().expect("We just peeked this line");
+            if !next.trim_start().starts_with("Use -p ") {
+                message.push(*next);
+            }
+        }
+
+        let location = message
+            .iter()
+            .position(|line| parse_location(line).is_some());
+        let (path, span) = match location {
+            Some(index) => {
+                let (path, span) =
+                    parse_location(message.remove(index)).expect("We just parsed this location");
+                (Some(path), span)
+            }
+            None => (None, PositionRange::default()),
+        };
+
+        let name = groups
+            .iter()
+            .map(|(_, group)| *group)
+            .chain([name.trim()])
+            .collect::<Vec<_>>()
+            .join(".");
+
+        failures.push(TestFailure {
+            name,
+            path,
+            span,
+            message: dedent(&message),
+        });
+    }
+
+    Some(TestResults {
+        framework: TestFramework::Tasty,
+        passed,
+        failed,
+        pending: 0,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_hspec() {
+        assert_eq!(
+            parse_test_results(indoc!(
+                r#"
+                Foo
+                  bar
+                    does the thing [✔]
+                    does the other thing [✘]
+                    is pending [‐]
+                      # PENDING: No reason given
+
+                Failures:
+
+                  test/FooSpec.hs:10:5:
+                  1) Foo.bar does the other thing
+                       expected: 1
+                        but got: 2
+
+                  To rerun use: --match "/Foo/bar/does the other thing/"
+
+                  2) Foo.bar crashes
+                       uncaught exception: ErrorCall
+                       oops
+
+                  To rerun use: --match "/Foo/bar/crashes/"
+
+                Randomized with seed 1234
+
+                Finished in 0.0012 seconds
+                5 examples, 2 failures, 1 pending
+                "#
+            )),
+            Some(TestResults {
+                framework: TestFramework::Hspec,
+                passed: 2,
+                failed: 2,
+                pending: 1,
+                failures: vec![
+                    TestFailure {
+                        name: "Foo.bar does the other thing".into(),
+                        path: Some("test/FooSpec.hs".into()),
+                        span: PositionRange::new(10, 5, 10, 5),
+                        message: "expected: 1\n but got: 2".into(),
+                    },
+                    TestFailure {
+                        name: "Foo.bar crashes".into(),
+                        path: None,
+                        span: PositionRange::default(),
+                        message: "uncaught exception: ErrorCall\noops".into(),
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_hspec_success() {
+        assert_eq!(
+            parse_test_results(indoc!(
+                "
+                Foo
+                  does the thing [✔]
+
+                Finished in 0.0001 seconds
+                1 example, 0 failures
+                "
+            )),
+            Some(TestResults {
+                framework: TestFramework::Hspec,
+                passed: 1,
+                failed: 0,
+                pending: 0,
+                failures: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_tasty() {
+        assert_eq!(
+            parse_test_results(indoc!(
+                "
+                Tests
+                  Foo
+                    bar:        OK
+                    baz:        FAIL (0.01s)
+                      test/FooTest.hs:12:
+                      expected: 1
+                       but got: 2
+                      Use -p '/baz/' to rerun this test only.
+                  Qux:          OK
+
+                1 out of 3 tests failed (0.02s)
+                "
+            )),
+            Some(TestResults {
+                framework: TestFramework::Tasty,
+                passed: 2,
+                failed: 1,
+                pending: 0,
+                failures: vec![TestFailure {
+                    name: "Tests.Foo.baz".into(),
+                    path: Some("test/FooTest.hs".into()),
+                    span: PositionRange::new(12, 1, 12, 1),
+                    message: "expected: 1\n but got: 2".into(),
+                }],
+            })
+        );
+
+        assert_eq!(
+            parse_test_results("Tests\n  foo: OK\n\nAll 1 tests passed (0.00s)\n"),
+            Some(TestResults {
+                framework: TestFramework::Tasty,
+                passed: 1,
+                failed: 0,
+                pending: 0,
+                failures: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_no_tests() {
+        assert_eq!(parse_test_results("Ok, 1 module loaded.\n"), None);
+    }
+
+    #[test]
+    fn test_failure_to_diagnostic() {
+        assert_eq!(
+            TestFailure {
+                name: "Foo.bar".into(),
+                path: Some("test/FooSpec.hs".into()),
+                span: PositionRange::new(10, 5, 10, 5),
+                message: "expected: 1\n but got: 2".into(),
+            }
+            .to_diagnostic()
+            .to_string(),
+            indoc!(
+                "
+                test/FooSpec.hs:10:5: error:
+                    Test failed: Foo.bar
+                    expected: 1
+                     but got: 2
+                "
+            )
+        );
+    }
+}
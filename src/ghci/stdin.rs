@@ -5,6 +5,7 @@ use tokio::process::ChildStdin;
 use tracing::instrument;
 
 use crate::incremental_reader::FindAt;
+use crate::path_map::PathMap;
 
 use super::loaded_module::LoadedModule;
 use super::parse::ShowPaths;
@@ -17,6 +18,8 @@ use crate::ghci::GhciStdout;
 pub struct GhciStdin {
     /// Inner stdin writer.
     pub stdin: ChildStdin,
+    /// Mappings from paths `ghciwatch` sees to paths `ghci` sees.
+    pub path_map: PathMap,
 }
 
 impl GhciStdin {
@@ -67,6 +70,26 @@ impl GhciStdin {
         Ok(())
     }
 
+    /// Run a [`GhciCommand`], returning its output.
+    ///
+    /// The output includes everything written to `stdout`, followed by everything written to
+    /// `stderr`.
+    #[instrument(skip(self, stdout), level = "debug")]
+    pub async fn run_command_output(
+        &mut self,
+        stdout: &mut GhciStdout,
+        command: &GhciCommand,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<String> {
+        let mut output = String::new();
+        for line in command.lines() {
+            self.stdin.write_all(format!("{line}\n").as_bytes()).await?;
+            output.push_str(&stdout.prompt_output(FindAt::LineStart, log).await?);
+        }
+
+        Ok(output)
+    }
+
     /// Write `:set prompt "{prompt}"\n` to stdin without reading any response.
     ///
     /// Callers that need to wait for GHCi to acknowledge the new prompt should use
@@ -117,6 +140,24 @@ impl GhciStdin {
         self.write_line(stdout, ":reload\n", log).await
     }
 
+    /// Turn `-fforce-recomp` on or off, so that `:reload` and `:add` recompile modules even if
+    /// GHC thinks they're up to date.
+    #[instrument(skip(self, stdout), level = "debug")]
+    pub async fn set_force_recomp(
+        &mut self,
+        stdout: &mut GhciStdout,
+        force_recomp: bool,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<()> {
+        let flag = if force_recomp {
+            "-fforce-recomp"
+        } else {
+            "-fno-force-recomp"
+        };
+        self.write_line(stdout, &format!(":set {flag}\n"), log)
+            .await
+    }
+
     #[instrument(skip_all, level = "debug")]
     pub async fn add_modules(
         &mut self,
@@ -124,7 +165,10 @@ impl GhciStdin {
         modules: impl IntoIterator<Item = &LoadedModule>,
         log: &mut CompilationLog,
     ) -> eyre::Result<()> {
-        let modules = modules.into_iter().format(" ");
+        let modules = modules
+            .into_iter()
+            .map(|module| module.for_ghci(&self.path_map))
+            .join(" ");
         // We use `:add` because `:load` unloads all previously loaded modules:
         //
         // > All previously loaded modules, except package modules, are forgotten. The new set of
@@ -143,7 +187,10 @@ impl GhciStdin {
         modules: impl IntoIterator<Item = &LoadedModule>,
         log: &mut CompilationLog,
     ) -> eyre::Result<()> {
-        let modules = modules.into_iter().format(" ");
+        let modules = modules
+            .into_iter()
+            .map(|module| module.for_ghci(&self.path_map))
+            .join(" ");
         self.write_line(stdout, &format!(":unadd {modules}\n"), log)
             .await
     }
@@ -157,6 +204,7 @@ impl GhciStdin {
     ) -> eyre::Result<()> {
         // `:add *` forces the module to be interpreted, even if it was already loaded from
         // bytecode. This is necessary to access the module's top-level binds for the eval feature.
+        let module = module.for_ghci(&self.path_map);
         self.write_line(stdout, &format!(":add *{module}\n"), log)
             .await
     }
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use aho_corasick::AhoCorasick;
+use camino::Utf8Path;
 use eyre::Context;
 use tokio::process::ChildStdout;
 use tokio::sync::mpsc;
@@ -12,10 +13,13 @@ use crate::incremental_reader::FindAt;
 use crate::incremental_reader::IncrementalReader;
 use crate::incremental_reader::ReadOpts;
 use crate::incremental_reader::WriteBehavior;
+use crate::path_map::PathMap;
 
 use super::parse::parse_ghc_messages;
 use super::parse::parse_show_paths;
 use super::parse::parse_show_targets;
+use super::parse::parse_test_results;
+use super::parse::GhcMessage;
 use super::parse::ShowPaths;
 use super::stderr::StderrEvent;
 use super::writer::GhciWriter;
@@ -32,11 +36,17 @@ pub struct GhciStdout {
     pub prompt_patterns: AhoCorasick,
     /// A buffer to read data into. Lets us avoid allocating buffers in the [`IncrementalReader`].
     pub buffer: Vec<u8>,
+    /// Mappings from paths `ghci` sees to paths `ghciwatch` sees.
+    pub path_map: PathMap,
+    /// Whether to parse test results from the output. This is set while `Test` hooks are running,
+    /// so that other output which happens to look like a test summary isn't parsed.
+    pub parse_test_results: bool,
 }
 
 impl GhciStdout {
+    /// Parse the output for a prompt into the `log`, returning the `stderr` output.
     #[instrument(skip_all, level = "debug")]
-    async fn parse_into_log(&self, data: &str, log: &mut CompilationLog) -> eyre::Result<()> {
+    async fn parse_into_log(&self, data: &str, log: &mut CompilationLog) -> eyre::Result<String> {
         // Parse GHCi output into compiler messages.
         //
         // These include diagnostics, which modules were compiled, and a compilation summary.
@@ -48,9 +58,28 @@ impl GhciStdout {
                 .await;
             receiver.await?
         };
-        log.extend(parse_ghc_messages(data).wrap_err("Failed to parse compiler output")?);
-        log.extend(parse_ghc_messages(&stderr_data).wrap_err("Failed to parse compiler output")?);
-        Ok(())
+        for data in [data, &stderr_data] {
+            let messages = parse_ghc_messages(data).wrap_err("Failed to parse compiler output")?;
+            log.extend(
+                messages
+                    .into_iter()
+                    .map(|message| self.map_message_paths(message)),
+            );
+        }
+        // Test runners like `hspec` and `tasty` may print their results to either stream.
+        if self.parse_test_results {
+            if let Some(mut results) =
+                parse_test_results(data).or_else(|| parse_test_results(&stderr_data))
+            {
+                for failure in &mut results.failures {
+                    if let Some(path) = &mut failure.path {
+                        *path = self.path_map.to_host(path);
+                    }
+                }
+                log.test_results.push(results);
+            }
+        }
+        Ok(stderr_data)
     }
 
     #[instrument(skip_all, name = "stdout_initialize", level = "debug")]
@@ -79,6 +108,20 @@ impl GhciStdout {
 
     #[instrument(skip_all, level = "debug")]
     pub async fn prompt(&mut self, find: FindAt, log: &mut CompilationLog) -> eyre::Result<()> {
+        self.prompt_output(find, log).await?;
+        Ok(())
+    }
+
+    /// Wait for a prompt, returning the output before it.
+    ///
+    /// The output includes everything written to `stdout`, followed by everything written to
+    /// `stderr`.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn prompt_output(
+        &mut self,
+        find: FindAt,
+        log: &mut CompilationLog,
+    ) -> eyre::Result<String> {
         self.stderr_sender.send(StderrEvent::ClearBuffer).await?;
 
         let data = self
@@ -92,8 +135,8 @@ impl GhciStdout {
             .await?;
         tracing::debug!(bytes = data.len(), "Got data from ghci");
 
-        self.parse_into_log(&data, log).await?;
-        Ok(())
+        let stderr_data = self.parse_into_log(&data, log).await?;
+        Ok(data + &stderr_data)
     }
 
     /// Read any immediately-available output from the pipe, then drain stale prompts from
@@ -141,7 +184,13 @@ impl GhciStdout {
                 buffer: &mut self.buffer,
             })
             .await?;
-        parse_show_paths(&lines).wrap_err("Failed to parse `:show paths` output")
+        let mut show_paths =
+            parse_show_paths(&lines).wrap_err("Failed to parse `:show paths` output")?;
+        show_paths.cwd = self.path_map.to_host(&show_paths.cwd);
+        for path in show_paths.search_paths.iter_mut() {
+            *path = self.path_map.to_host(path);
+        }
+        Ok(show_paths)
     }
 
     #[instrument(skip_all, level = "debug")]
@@ -155,9 +204,30 @@ impl GhciStdout {
                 buffer: &mut self.buffer,
             })
             .await?;
+        // Targets may be absolute paths, which need to be mapped.
+        let lines = lines
+            .lines()
+            .map(|line| format!("{}\n", self.path_map.to_host(Utf8Path::new(line))))
+            .collect::<String>();
         parse_show_targets(search_paths, &lines).wrap_err("Failed to parse `:show targets` output")
     }
 
+    /// Map the paths in a compiler message from paths `ghci` sees to paths `ghciwatch` sees.
+    fn map_message_paths(&self, mut message: GhcMessage) -> GhcMessage {
+        match &mut message {
+            GhcMessage::Compiling(progress) => {
+                progress.module.path = self.path_map.to_host(&progress.module.path);
+            }
+            GhcMessage::Diagnostic(diagnostic) => {
+                if let Some(path) = &mut diagnostic.path {
+                    *path = self.path_map.to_host(path);
+                }
+            }
+            _ => {}
+        }
+        message
+    }
+
     #[allow(dead_code)] // TODO: No it should not be!
     #[instrument(skip_all, level = "debug")]
     pub async fn quit(&mut self) -> eyre::Result<()> {
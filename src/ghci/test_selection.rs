@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+
+use eyre::eyre;
+
+/// The placeholder for the tested module's name in a [`SpecModuleFormat`].
+const MODULE_PLACEHOLDER: &str = "{module}";
+
+/// A naming convention mapping modules to the spec modules which test them, like `{module}Spec`
+/// (which maps `Foo.Bar` to `Foo.BarSpec`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecModuleFormat {
+    prefix: String,
+    suffix: String,
+}
+
+impl SpecModuleFormat {
+    /// Parse a spec module format containing `{module}` exactly once.
+    pub fn new(format: &str) -> eyre::Result<Self> {
+        let (prefix, suffix) = format.split_once(MODULE_PLACEHOLDER).ok_or_else(|| {
+            eyre!("Spec module format must contain `{MODULE_PLACEHOLDER}`: {format:?}")
+        })?;
+
+        if suffix.contains(MODULE_PLACEHOLDER) {
+            return Err(eyre!(
+                "Spec module format must contain `{MODULE_PLACEHOLDER}` only once: {format:?}"
+            ));
+        }
+
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err(eyre!(
+                "Spec module format must contain more than `{MODULE_PLACEHOLDER}`: {format:?}"
+            ));
+        }
+
+        Ok(Self {
+            prefix: prefix.to_owned(),
+            suffix: suffix.to_owned(),
+        })
+    }
+
+    /// Get the name of the spec module for the given module.
+    pub fn spec_module(&self, module: &str) -> String {
+        format!("{}{module}{}", self.prefix, self.suffix)
+    }
+
+    /// Is the given module a spec module?
+    pub fn is_spec_module(&self, module: &str) -> bool {
+        module
+            .strip_prefix(&self.prefix)
+            .and_then(|rest| rest.strip_suffix(&self.suffix))
+            .is_some_and(|tested| !tested.is_empty())
+    }
+
+    /// Select the spec modules to run for the given compiled modules.
+    ///
+    /// Compiled spec modules are selected themselves, and other compiled modules select their
+    /// spec modules. Only spec modules in `loaded_modules` are selected.
+    pub fn select<'a>(
+        &self,
+        compiled_modules: impl IntoIterator<Item = &'a str>,
+        loaded_modules: &BTreeSet<String>,
+    ) -> BTreeSet<String> {
+        compiled_modules
+            .into_iter()
+            .map(|module| {
+                if self.is_spec_module(module) {
+                    module.to_owned()
+                } else {
+                    self.spec_module(module)
+                }
+            })
+            .filter(|spec_module| loaded_modules.contains(spec_module))
+            .collect()
+    }
+
+    /// Get arguments selecting the given spec modules in an `hspec` test suite.
+    ///
+    /// `hspec-discover` names the spec items in each spec module after the module, without its
+    /// suffix, so with the format `{module}Spec`, `Foo.BarSpec` is matched with
+    /// `--match /Foo.Bar/`.
+    pub fn hspec_match_args<'a>(
+        &self,
+        spec_modules: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<String> {
+        spec_modules
+            .into_iter()
+            .flat_map(|spec_module| {
+                let name = spec_module
+                    .strip_suffix(&self.suffix)
+                    .unwrap_or(spec_module);
+                ["--match".to_owned(), format!("/{name}/")]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            SpecModuleFormat::new("{module}Spec").unwrap(),
+            SpecModuleFormat {
+                prefix: "".into(),
+                suffix: "Spec".into(),
+            }
+        );
+        assert_eq!(
+            SpecModuleFormat::new("Test.{module}Spec").unwrap(),
+            SpecModuleFormat {
+                prefix: "Test.".into(),
+                suffix: "Spec".into(),
+            }
+        );
+        assert!(SpecModuleFormat::new("Spec").is_err());
+        assert!(SpecModuleFormat::new("{module}").is_err());
+        assert!(SpecModuleFormat::new("{module}Spec{module}").is_err());
+    }
+
+    #[test]
+    fn test_spec_module() {
+        let format = SpecModuleFormat::new("{module}Spec").unwrap();
+        assert_eq!(format.spec_module("Foo.Bar"), "Foo.BarSpec");
+        assert!(format.is_spec_module("Foo.BarSpec"));
+        assert!(!format.is_spec_module("Foo.Bar"));
+        assert!(!format.is_spec_module("Spec"));
+    }
+
+    #[test]
+    fn test_select() {
+        let format = SpecModuleFormat::new("{module}Spec").unwrap();
+        let loaded_modules = ["Foo", "FooSpec", "Foo.Bar", "Foo.BarSpec", "Baz", "Main"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            format.select(["Foo.Bar", "FooSpec", "Baz", "Main"], &loaded_modules),
+            ["Foo.BarSpec", "FooSpec"]
+                .into_iter()
+                .map(String::from)
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_hspec_match_args() {
+        let format = SpecModuleFormat::new("{module}Spec").unwrap();
+        assert_eq!(
+            format.hspec_match_args(&["Foo.BarSpec".to_owned(), "Baz".to_owned()]),
+            ["--match", "/Foo.Bar/", "--match", "/Baz/"]
+        );
+
+        let format = SpecModuleFormat::new("Test.{module}Test").unwrap();
+        assert_eq!(
+            format.hspec_match_args(&["Test.Foo.BarTest".to_owned()]),
+            ["--match", "/Test.Foo.Bar/"]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Files written by `ghciwatch` itself, like eval output written back into source files.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use camino::Utf8PathBuf;
+use eyre::WrapErr;
+
+use crate::content_hashes::hash;
+use crate::content_hashes::read_hash;
+use crate::normal_path::NormalPath;
+
+/// Files written by `ghciwatch`, and hashes of the contents it wrote.
+///
+/// Writing a file produces a file event like any other edit, so this is shared with the
+/// [`super::FileClassifier`]s to ignore events for files which haven't changed since `ghciwatch`
+/// wrote them. Otherwise, writing eval output into a file would trigger a reload, which would
+/// write the eval output again, and so on.
+#[derive(Debug, Clone, Default)]
+pub struct WrittenFiles {
+    hashes: Arc<Mutex<HashMap<Utf8PathBuf, u64>>>,
+}
+
+impl WrittenFiles {
+    /// Write `contents` to the file at `path`, remembering them so that the resulting file event
+    /// is ignored.
+    pub async fn write(&self, path: &NormalPath, contents: &str) -> eyre::Result<()> {
+        self.hashes
+            .lock()
+            .expect("Written files lock is poisoned")
+            .insert(path.absolute().to_owned(), hash(contents.as_bytes()));
+        tokio::fs::write(path, contents)
+            .await
+            .wrap_err_with(|| format!("Failed to write {path}"))
+    }
+
+    /// Is the file at `path` unchanged since `ghciwatch` last wrote it?
+    ///
+    /// Once the file is changed by something else, it's forgotten.
+    pub fn is_unchanged(&self, path: &NormalPath) -> bool {
+        let mut hashes = self.hashes.lock().expect("Written files lock is poisoned");
+        let Some(&written) = hashes.get(path.absolute()) else {
+            return false;
+        };
+
+        if read_hash(path.absolute()) == Some(written) {
+            true
+        } else {
+            hashes.remove(path.absolute());
+            false
+        }
+    }
+}
@@ -1,10 +1,12 @@
 //! Lifecycle hooks.
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fmt::Write;
-use std::process::ExitStatus;
 use std::str::FromStr;
+use std::time::Duration;
 
+use camino::Utf8Path;
 use clap::builder::ValueParserFactory;
 use clap::Arg;
 use clap::ArgAction;
@@ -12,22 +14,32 @@ use clap::Args;
 use clap::FromArgMatches;
 use enum_iterator::Sequence;
 use indoc::indoc;
-use tokio::task::JoinHandle;
 
+use crate::clap::DurationValueParser;
 use crate::ghci::GhciCommand;
+use crate::maybe_async_command::AsyncCommandHandle;
 use crate::maybe_async_command::MaybeAsyncCommand;
+use crate::placeholders::Placeholders;
 
 /// A lifecycle event that triggers hooks.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Sequence)]
 pub enum LifecycleEvent {
     /// When tests are run (after startup, after reloads).
-    Test,
+    ///
+    /// `None` indicates the tests themselves; `Some` indicates setup or teardown.
+    Test(Option<When>),
     /// When a `ghci` session is started (at `ghciwatch` startup and after restarts).
     Startup(When),
     /// When a module is changed or added.
     Reload(When),
     /// When a `ghci` session is restarted (when a module is removed or renamed).
     Restart(When),
+    /// After any compilation (startup, reload, or restart) succeeds.
+    Success,
+    /// After any compilation (startup, reload, or restart) fails.
+    Failure,
+    /// When `ghciwatch` exits.
+    Shutdown,
 }
 
 impl Display for LifecycleEvent {
@@ -43,30 +55,62 @@ impl LifecycleEvent {
     /// Get the event name, like `test` or `reload`.
     pub fn event_name(&self) -> &'static str {
         match self {
-            LifecycleEvent::Test => "test",
+            LifecycleEvent::Test(_) => "test",
             LifecycleEvent::Startup(_) => "startup",
             LifecycleEvent::Reload(_) => "reload",
             LifecycleEvent::Restart(_) => "restart",
+            LifecycleEvent::Success => "success",
+            LifecycleEvent::Failure => "failure",
+            LifecycleEvent::Shutdown => "shutdown",
         }
     }
 
     /// Get the noun form of the event name, like `testing` or `reloading`.
     pub fn event_noun(&self) -> &'static str {
         match self {
-            LifecycleEvent::Test => "testing",
+            LifecycleEvent::Test(_) => "testing",
             LifecycleEvent::Startup(_) => "starting up",
             LifecycleEvent::Reload(_) => "reloading",
             LifecycleEvent::Restart(_) => "restarting",
+            LifecycleEvent::Success => "a successful compilation",
+            LifecycleEvent::Failure => "a failed compilation",
+            LifecycleEvent::Shutdown => "shutting down",
+        }
+    }
+
+    /// Does this event occur after compilation has finished and the error log has been written?
+    pub fn is_after_compilation(&self) -> bool {
+        match self {
+            LifecycleEvent::Startup(When::Before)
+            | LifecycleEvent::Reload(When::Before)
+            | LifecycleEvent::Restart(When::Before)
+            | LifecycleEvent::Shutdown => false,
+            LifecycleEvent::Startup(When::After)
+            | LifecycleEvent::Reload(When::After)
+            | LifecycleEvent::Restart(When::After)
+            | LifecycleEvent::Success
+            | LifecycleEvent::Failure
+            | LifecycleEvent::Test(_) => true,
         }
     }
 
     fn get_message(&self) -> &'static str {
         match self {
-            LifecycleEvent::Test => indoc!(
+            LifecycleEvent::Test(None) => indoc!(
                 "
                 Tests are run after startup and after reloads.
                 "
             ),
+            LifecycleEvent::Test(Some(When::Before)) => indoc!(
+                "
+                Before-test hooks run before tests, and can be used to set up test fixtures.
+                "
+            ),
+            LifecycleEvent::Test(Some(When::After)) => indoc!(
+                "
+                After-test hooks run after tests, and can be used to tear down test fixtures.
+                "
+            ),
             LifecycleEvent::Startup(_) => indoc!(
                 "
                 Startup hooks run when GHCi is started (at `ghciwatch` startup and after GHCi restarts).
@@ -82,32 +126,98 @@ impl LifecycleEvent {
                 The GHCi session must be restarted when `.cabal` or `.ghci` files are modified.
                 "
             ),
+            LifecycleEvent::Success => indoc!(
+                "
+                Success hooks run after startup, reloads, and restarts compile successfully, before eval commands and tests are run.
+                "
+            ),
+            LifecycleEvent::Failure => indoc!(
+                "
+                Failure hooks run after startup, reloads, and restarts fail to compile, after the error log is written.
+                "
+            ),
+            LifecycleEvent::Shutdown => indoc!(
+                "
+                Shutdown hooks run when `ghciwatch` exits, after the GHCi session is stopped.
+                "
+            ),
         }.trim_end_matches('\n')
     }
 
     fn get_help_name(&self) -> Option<&'static str> {
         match self {
-            LifecycleEvent::Test => Some("tests"),
+            LifecycleEvent::Test(_) => Some("tests"),
+            LifecycleEvent::Success => Some("after compilation succeeds"),
+            LifecycleEvent::Failure => Some("after compilation fails"),
+            LifecycleEvent::Shutdown => Some("at shutdown"),
             _ => None,
         }
     }
 
+    /// Describe when hooks for this event run, like `before reload` or `tests`.
+    fn description(&self) -> String {
+        let mut description = String::new();
+
+        if let Some(when) = self.when() {
+            write!(description, "{} ", when).expect("Writing to a `String` never fails");
+        }
+
+        if let Some(help_name) = self.get_help_name() {
+            description.push_str(help_name);
+        } else {
+            description.push_str(self.event_name());
+        }
+
+        description
+    }
+
+    fn timeout_arg_name(&self) -> String {
+        format!("{self}-timeout")
+    }
+
+    fn timeout_help(&self) -> Help {
+        let short = format!("Timeout for each command to run {}", self.description());
+
+        let mut long = short.clone();
+
+        long.push_str("\n\n");
+        if self.supported_kind().contains(&CommandKind::Ghci) {
+            long.push_str(
+                "`ghci` commands which run longer than this are interrupted with `Ctrl-C`, and \
+                 shell commands",
+            );
+        } else {
+            long.push_str("Shell commands");
+        }
+        long.push_str(
+            " which run longer than this are killed along with any processes they spawned. \
+             Timed-out commands are reported as failures.",
+        );
+
+        Help { short, long }
+    }
+
     fn when(&self) -> Option<When> {
         match &self {
-            LifecycleEvent::Test => None,
+            LifecycleEvent::Test(when) => *when,
             LifecycleEvent::Startup(when) => Some(*when),
             LifecycleEvent::Reload(when) => Some(*when),
             LifecycleEvent::Restart(when) => Some(*when),
+            LifecycleEvent::Success | LifecycleEvent::Failure | LifecycleEvent::Shutdown => None,
         }
     }
 
     fn supported_kind(&self) -> Vec<CommandKind> {
         match self {
-            LifecycleEvent::Startup(When::Before) => vec![CommandKind::Shell],
+            LifecycleEvent::Startup(When::Before) | LifecycleEvent::Shutdown => {
+                vec![CommandKind::Shell]
+            }
             LifecycleEvent::Startup(When::After)
-            | LifecycleEvent::Test
+            | LifecycleEvent::Test(_)
             | LifecycleEvent::Reload(_)
-            | LifecycleEvent::Restart(_) => {
+            | LifecycleEvent::Restart(_)
+            | LifecycleEvent::Success
+            | LifecycleEvent::Failure => {
                 vec![CommandKind::Ghci, CommandKind::Shell]
             }
         }
@@ -186,6 +296,14 @@ impl Command {
             Command::Shell(_) => CommandKind::Shell,
         }
     }
+
+    /// Expand [`Placeholders`] like `{changed_files}` in this command.
+    pub fn expand(&self, placeholders: &Placeholders) -> Self {
+        match self {
+            Command::Ghci(command) => Command::Ghci(command.expand(placeholders)),
+            Command::Shell(command) => Command::Shell(command.expand(placeholders)),
+        }
+    }
 }
 
 impl Display for Command {
@@ -234,7 +352,7 @@ impl Hook<CommandKind> {
                 Use `:set args ...` to set command-line arguments for test hooks.
                 ",
             )),
-            (LifecycleEvent::Test, CommandKind::Ghci) => Some(indoc!(
+            (LifecycleEvent::Test(None), CommandKind::Ghci) => Some(indoc!(
                 "
                 Example: `TestMain.testMain`.
                 ",
@@ -255,19 +373,7 @@ impl Hook<CommandKind> {
             CommandKind::Shell => "Shell",
         };
 
-        let mut short = format!("{kind} commands to run");
-
-        if let Some(when) = self.event.when() {
-            short.push(' ');
-            write!(short, "{}", when).expect("Writing to a `String` never fails");
-        }
-
-        short.push(' ');
-        if let Some(help_name) = event.get_help_name() {
-            short.push_str(help_name);
-        } else {
-            write!(short, "{}", event.event_name()).expect("Writing to a `String` never fails");
-        }
+        let short = format!("{kind} commands to run {}", event.description());
 
         let mut long = short.clone();
 
@@ -294,6 +400,54 @@ struct Help {
     long: String,
 }
 
+/// Handles for `async:` shell hooks running in the background, grouped by the event they were
+/// run for.
+#[derive(Debug, Default)]
+pub struct HookHandles(BTreeMap<LifecycleEvent, Vec<AsyncCommandHandle>>);
+
+impl HookHandles {
+    /// Get the handles for hooks run for the given event.
+    pub fn for_event(&mut self, event: LifecycleEvent) -> &mut Vec<AsyncCommandHandle> {
+        self.0.entry(event).or_default()
+    }
+
+    /// Get rid of any handles for background commands that have finished.
+    pub fn prune(&mut self) {
+        for handles in self.0.values_mut() {
+            handles.retain(|handle| !handle.is_finished());
+        }
+        self.0.retain(|_event, handles| !handles.is_empty());
+    }
+
+    /// Cancel any background commands still running for the given event.
+    pub fn cancel(&mut self, event: LifecycleEvent) {
+        for handle in self.0.remove(&event).into_iter().flatten() {
+            if !handle.is_finished() {
+                tracing::info!(command = %handle, "Cancelling superseded {event} command");
+                handle.cancel();
+            }
+        }
+    }
+
+    /// Wait for all background commands to finish.
+    pub async fn wait(self) -> eyre::Result<()> {
+        for handle in self.0.into_values().flatten() {
+            handle.wait().await?;
+        }
+        Ok(())
+    }
+
+    /// Move the handles from `other` into this set of handles.
+    pub fn append(&mut self, other: HookHandles) {
+        for (event, mut handles) in other.0 {
+            self.for_event(event).append(&mut handles);
+        }
+    }
+}
+
+/// The name of the `--cancel-superseded-async-hooks` argument.
+const CANCEL_SUPERSEDED_ASYNC_HOOKS: &str = "cancel-superseded-async-hooks";
+
 /// Lifecycle hooks.
 ///
 /// These are `ghci` and shell commands to run at various points in the `ghciwatch`
@@ -301,26 +455,57 @@ struct Help {
 #[derive(Debug, Clone, Default)]
 pub struct HookOpts {
     hooks: Vec<Hook<Command>>,
+    timeouts: BTreeMap<LifecycleEvent, Duration>,
+    cancel_superseded_async_hooks: bool,
 }
 
 impl HookOpts {
+    /// Get the timeout for each command run for the given event, if any.
+    pub fn timeout(&self, event: LifecycleEvent) -> Option<Duration> {
+        self.timeouts.get(&event).copied()
+    }
+
+    /// Cancel `async:` shell hooks still running from a previous `event`, if
+    /// `--cancel-superseded-async-hooks` is set.
+    pub fn cancel_superseded(&self, event: LifecycleEvent, handles: &mut HookHandles) {
+        if self.cancel_superseded_async_hooks {
+            handles.cancel(event);
+        }
+    }
+
     pub fn select(&self, event: LifecycleEvent) -> impl Iterator<Item = &Hook<Command>> {
         self.hooks.iter().filter(move |hook| hook.event == event)
     }
 
+    /// Run the shell hooks for `placeholders.event`.
     pub async fn run_shell_hooks(
         &self,
-        event: LifecycleEvent,
-        handles: &mut Vec<JoinHandle<eyre::Result<ExitStatus>>>,
+        placeholders: &Placeholders,
+        handles: &mut HookHandles,
     ) -> eyre::Result<()> {
+        let event = placeholders.event;
+        self.cancel_superseded(event, handles);
+        let timeout = self.timeout(event);
         for hook in self.select(event) {
             if let Command::Shell(command) = &hook.command {
+                let command = command.expand(placeholders);
                 tracing::info!(%command, "Running {hook} command");
-                command.run_on(handles).await?;
+                command.run_on(handles.for_event(event), timeout).await?;
             }
         }
         Ok(())
     }
+
+    /// Run the [`LifecycleEvent::Shutdown`] hooks and wait for any `async:` commands to finish.
+    pub async fn run_shutdown_hooks(&self, error_file: Option<&Utf8Path>) -> eyre::Result<()> {
+        let mut handles = HookHandles::default();
+        let placeholders = Placeholders {
+            error_file: error_file.map(ToString::to_string),
+            ..Placeholders::new(LifecycleEvent::Shutdown)
+        };
+        self.run_shell_hooks(&placeholders, &mut handles).await?;
+        handles.wait().await
+    }
 }
 
 impl Args for HookOpts {
@@ -344,7 +529,37 @@ impl Args for HookOpts {
 
             cmd = cmd.arg(arg);
         }
-        cmd
+
+        for event in enum_iterator::all::<LifecycleEvent>() {
+            let name = event.timeout_arg_name();
+            let help = event.timeout_help();
+            cmd = cmd.arg(
+                Arg::new(&name)
+                    .long(&name)
+                    .action(ArgAction::Set)
+                    .required(false)
+                    .value_name("DURATION")
+                    .value_parser(DurationValueParser::default())
+                    .help(help.short)
+                    .long_help(help.long)
+                    .help_heading("Lifecycle hooks"),
+            );
+        }
+
+        cmd.arg(
+            Arg::new(CANCEL_SUPERSEDED_ASYNC_HOOKS)
+                .long(CANCEL_SUPERSEDED_ASYNC_HOOKS)
+                .action(ArgAction::SetTrue)
+                .help("Kill `async:` shell commands still running from a previous event when the same event occurs again")
+                .long_help(indoc!(
+                    "
+                    Kill `async:` shell commands still running from a previous event when the same event occurs again.
+
+                    For example, with `--after-reload-shell 'async:hlint src'`, an `hlint` run from a previous reload will be killed (along with any processes it spawned) when the next reload finishes, so that only the results of the latest run are shown.
+                    "
+                ).trim_end_matches('\n'))
+                .help_heading("Lifecycle hooks"),
+        )
     }
 
     fn augment_args_for_update(cmd: clap::Command) -> clap::Command {
@@ -384,12 +599,19 @@ impl FromArgMatches for HookOpts {
             }
         }
 
+        self.cancel_superseded_async_hooks |= matches.get_flag(CANCEL_SUPERSEDED_ASYNC_HOOKS);
+
+        for event in enum_iterator::all::<LifecycleEvent>() {
+            if let Some(timeout) = matches.get_one::<Duration>(&event.timeout_arg_name()) {
+                self.timeouts.insert(event, *timeout);
+            }
+        }
+
         // Sort the hooks so that shell commands are first.
         //
         // Shell commands _may_ be asynchronous, but `ghci` commands are always synchronous, so we
         // run shell commands first.
-        self.hooks
-            .sort_by(|a, b| a.command.kind().cmp(&b.command.kind()));
+        self.hooks.sort_by_key(|hook| hook.command.kind());
 
         Ok(())
     }
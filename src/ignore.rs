@@ -1,6 +1,11 @@
 //! Extensions and utilities for the [`ignore`] crate.
 
+use std::collections::HashMap;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 use eyre::Context;
 use ignore::gitignore::Gitignore;
@@ -87,6 +92,138 @@ impl GlobMatcher {
     }
 }
 
+/// The names of the ignore files read by [`IgnoreFiles`], from lowest to highest precedence.
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".ghciwatchignore"];
+
+/// Ignore files (`.gitignore`, `.ignore`, and `.ghciwatchignore`) in the directories containing
+/// changed paths.
+///
+/// Like Git, ignore files apply to the directory they're in and all its subdirectories, and ignore
+/// files in deeper directories take precedence. Ignore files are read up to the root of the Git
+/// repository, or up to the root of the filesystem outside of a Git repository.
+///
+/// Matchers are built lazily and cached, and rebuilt when an ignore file changes. Ignore files
+/// outside the watched paths (like a `.gitignore` at the root of the repository) don't produce
+/// file events, so their modification times are checked whenever they're used. Clones share the
+/// same cache.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreFiles {
+    directories: Arc<Mutex<HashMap<PathBuf, IgnoreDirectory>>>,
+}
+
+/// The ignore files in a single directory.
+#[derive(Debug)]
+struct IgnoreDirectory {
+    /// A matcher for all the ignore files in this directory.
+    matcher: Gitignore,
+    /// Is this directory the root of a Git repository?
+    is_repository_root: bool,
+    /// The modification times of the ignore files in this directory when the matcher was built,
+    /// or `None` for missing files.
+    modified: [Option<SystemTime>; IGNORE_FILE_NAMES.len()],
+}
+
+impl IgnoreDirectory {
+    fn new(dir: &Path) -> Self {
+        let modified = Self::modified(dir);
+        let mut builder = GitignoreBuilder::new(dir);
+        for name in IGNORE_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                if let Some(err) = builder.add(&path) {
+                    tracing::warn!("Failed to read {}: {err}", path.display());
+                }
+            }
+        }
+
+        let matcher = builder.build().unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to build ignore matcher for {}: {err}",
+                dir.display()
+            );
+            Gitignore::empty()
+        });
+
+        Self {
+            matcher,
+            is_repository_root: dir.join(".git").exists(),
+            modified,
+        }
+    }
+
+    /// Get the modification times of the ignore files in `dir`.
+    fn modified(dir: &Path) -> [Option<SystemTime>; IGNORE_FILE_NAMES.len()] {
+        IGNORE_FILE_NAMES.map(|name| {
+            dir.join(name)
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+    }
+
+    /// Have the ignore files in `dir` been created, changed, or removed since the matcher was
+    /// built?
+    fn is_stale(&self, dir: &Path) -> bool {
+        Self::modified(dir) != self.modified
+    }
+}
+
+impl IgnoreFiles {
+    /// Is the given absolute path ignored by an ignore file?
+    ///
+    /// If `path` is itself an ignore file, or the ignore files in one of its ancestors have
+    /// changed, the cached matchers are rebuilt first.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let mut directories = self
+            .directories
+            .lock()
+            .expect("Ignore files lock is poisoned");
+
+        if path
+            .file_name()
+            .is_some_and(|name| IGNORE_FILE_NAMES.iter().any(|ignore| name == *ignore))
+        {
+            if let Some(parent) = path.parent() {
+                directories.remove(parent);
+            }
+        }
+
+        let is_dir = path.is_dir();
+        for dir in path.ancestors().skip(1) {
+            let directory = directories
+                .entry(dir.to_owned())
+                .and_modify(|directory| {
+                    if directory.is_stale(dir) {
+                        *directory = IgnoreDirectory::new(dir);
+                    }
+                })
+                .or_insert_with(|| IgnoreDirectory::new(dir));
+
+            match directory.matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::None => {}
+                Match::Ignore(glob) => {
+                    tracing::trace!(
+                        path = %path.display(),
+                        glob = glob.original(),
+                        from = ?glob.from(),
+                        "Path is ignored"
+                    );
+                    return true;
+                }
+                Match::Whitelist(_) => {
+                    return false;
+                }
+            }
+
+            if directory.is_repository_root {
+                break;
+            }
+        }
+
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +265,60 @@ mod tests {
             .is_whitelist());
     }
 
+    #[test]
+    fn test_ignore_files() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = tempdir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src/generated")).unwrap();
+        std::fs::write(root.join(".gitignore"), "generated/\n*.tmp\n").unwrap();
+        std::fs::write(root.join("src/.ghciwatchignore"), "Scratch.hs\n!Keep.tmp\n").unwrap();
+
+        let ignore_files = IgnoreFiles::default();
+        assert!(!ignore_files.is_ignored(&root.join("src/MyLib.hs")));
+        assert!(ignore_files.is_ignored(&root.join("src/generated/MyLib.hs")));
+        assert!(ignore_files.is_ignored(&root.join("src/Foo.tmp")));
+        assert!(ignore_files.is_ignored(&root.join("src/Scratch.hs")));
+        assert!(!ignore_files.is_ignored(&root.join("Scratch.hs")));
+        // Deeper ignore files take precedence.
+        assert!(!ignore_files.is_ignored(&root.join("src/Keep.tmp")));
+
+        // Changing an ignore file rebuilds its matcher.
+        std::fs::write(root.join("src/.ghciwatchignore"), "").unwrap();
+        assert!(!ignore_files.is_ignored(&root.join("src/.ghciwatchignore")));
+        assert!(!ignore_files.is_ignored(&root.join("src/Scratch.hs")));
+    }
+
+    /// Test that changes to ignore files are noticed without file events for them, like when
+    /// they're outside the watched paths.
+    #[test]
+    fn test_ignore_files_changed_without_events() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = tempdir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let gitignore = root.join(".gitignore");
+        std::fs::write(&gitignore, "*.tmp\n").unwrap();
+
+        let ignore_files = IgnoreFiles::default();
+        assert!(ignore_files.is_ignored(&root.join("src/Foo.tmp")));
+        assert!(!ignore_files.is_ignored(&root.join("src/Scratch.hs")));
+
+        std::fs::write(&gitignore, "Scratch.hs\n").unwrap();
+        // Make sure the modification time changes, even on filesystems with coarse timestamps.
+        std::fs::File::options()
+            .write(true)
+            .open(&gitignore)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        assert!(!ignore_files.is_ignored(&root.join("src/Foo.tmp")));
+        assert!(ignore_files.is_ignored(&root.join("src/Scratch.hs")));
+
+        std::fs::remove_file(&gitignore).unwrap();
+        assert!(!ignore_files.is_ignored(&root.join("src/Scratch.hs")));
+    }
+
     /// Test that the last matching pattern wins.
     #[test]
     fn test_glob_ext_ordering() {
@@ -222,10 +222,7 @@ where
     /// Cancel-safe: if dropped mid-processing, unprocessed data remains in `self.pending`
     /// and will be drained on the next call.
     async fn drain_pending(&mut self, writing: WriteBehavior) -> eyre::Result<()> {
-        loop {
-            let Some(newline_idx) = self.pending.find('\n') else {
-                break;
-            };
+        while let Some(newline_idx) = self.pending.find('\n') {
             self.line.push_str(&self.pending[..newline_idx]);
             self.pending.replace_range(..newline_idx + 1, "");
             self.finish_line(writing).await?;
@@ -16,13 +16,16 @@
 
 mod aho_corasick;
 mod buffers;
+mod bulk_change;
 mod clap;
 pub mod clap_markdown;
 pub mod cli;
 mod clonable_command;
 mod command_ext;
+mod content_hashes;
 mod cwd;
 mod event_filter;
+mod event_recording;
 mod format_bulleted_list;
 mod ghci;
 mod haskell_source_file;
@@ -31,6 +34,9 @@ mod ignore;
 mod incremental_reader;
 mod maybe_async_command;
 mod normal_path;
+mod path_map;
+mod placeholders;
+mod save_notifications;
 mod shutdown;
 mod string_case;
 mod tracing;
@@ -42,11 +48,16 @@ pub(crate) use cwd::current_dir_utf8;
 pub(crate) use format_bulleted_list::format_bulleted_list;
 pub(crate) use string_case::StringCase;
 
+pub use event_recording::run_event_recorder;
+pub use event_recording::run_event_replay;
 pub use ghci::manager::run_ghci;
+pub use ghci::run_explain;
 pub use ghci::FileClassifier;
 pub use ghci::Ghci;
 pub use ghci::GhciOpts;
 pub use ghci::GhciWriter;
+pub use save_notifications::run_save_notifications;
+pub use save_notifications::SaveNotificationsOpts;
 pub use shutdown::ShutdownError;
 pub use shutdown::ShutdownHandle;
 pub use shutdown::ShutdownManager;
@@ -4,6 +4,8 @@
 //! `ghciwatch` watches your modules for changes and reloads them in a `ghci` session, displaying
 //! any errors.
 
+use std::collections::BTreeSet;
+use std::sync::Arc;
 use std::time::Duration;
 
 use clap::CommandFactory;
@@ -11,14 +13,20 @@ use clap::Parser;
 use eyre::eyre;
 use ghciwatch::cli;
 use ghciwatch::cli::ExperimentalFeature;
+use ghciwatch::run_event_recorder;
+use ghciwatch::run_event_replay;
+use ghciwatch::run_explain;
 use ghciwatch::run_ghci;
+use ghciwatch::run_save_notifications;
 use ghciwatch::run_tui;
 use ghciwatch::run_watcher;
 use ghciwatch::GhciOpts;
+use ghciwatch::SaveNotificationsOpts;
 use ghciwatch::ShutdownManager;
 use ghciwatch::TracingOpts;
 use ghciwatch::WatcherOpts;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
@@ -63,10 +71,28 @@ async fn main() -> eyre::Result<()> {
 
     std::env::set_var("IN_GHCIWATCH", "1");
 
-    let (ghci_sender, ghci_receiver) = mpsc::channel(32);
+    let (mut ghci_sender, ghci_receiver) = mpsc::channel(32);
 
-    let (ghci_opts, maybe_ghci_reader) = GhciOpts::from_cli(&opts)?;
-    let watcher_opts = WatcherOpts::from_cli(&opts);
+    let (mut ghci_opts, maybe_ghci_reader) = GhciOpts::from_cli(&opts)?;
+
+    if let Some(cli::Subcommand::Explain { paths }) = opts.subcommand.clone() {
+        let mut manager = ShutdownManager::with_timeout(Duration::from_secs(1));
+        manager
+            .spawn("run_explain", |handle| {
+                run_explain(handle, ghci_opts, paths)
+            })
+            .await;
+        return manager.wait_for_shutdown().await;
+    }
+
+    let mut watcher_opts = WatcherOpts::from_cli(&opts);
+
+    let (search_paths_sender, search_paths_receiver) = watch::channel(BTreeSet::new());
+    ghci_opts.watch_paths = Some(Arc::new(search_paths_sender));
+    watcher_opts.search_paths = Some(search_paths_receiver);
+
+    let (ready_sender, ready_receiver) = watch::channel(false);
+    ghci_opts.ready = Some(Arc::new(ready_sender));
 
     let mut manager = ShutdownManager::with_timeout(Duration::from_secs(1));
 
@@ -87,12 +113,46 @@ async fn main() -> eyre::Result<()> {
             run_ghci(handle, ghci_opts, ghci_receiver)
         })
         .await;
-    manager
-        .spawn("run_watcher", move |handle| {
-            run_watcher(handle, ghci_sender, watcher_opts)
-        })
-        .await;
+    if let Some(path) = opts.watch.record_events.clone() {
+        // Send file events through the recorder on their way to `ghci`.
+        let (recorder_sender, recorder_receiver) = mpsc::channel(32);
+        let ghci_sender = std::mem::replace(&mut ghci_sender, recorder_sender);
+        let ready_receiver = ready_receiver.clone();
+        manager
+            .spawn("run_event_recorder", move |handle| {
+                run_event_recorder(handle, recorder_receiver, ghci_sender, ready_receiver, path)
+            })
+            .await;
+    }
+    if let Some(path) = opts.watch.replay_events.clone() {
+        manager
+            .spawn("run_event_replay", move |handle| {
+                run_event_replay(handle, ghci_sender, ready_receiver, path)
+            })
+            .await;
+    } else {
+        if let Some(save_notifications_opts) = SaveNotificationsOpts::from_cli(&opts) {
+            let ghci_sender = ghci_sender.clone();
+            manager
+                .spawn("run_save_notifications", move |handle| {
+                    run_save_notifications(handle, ghci_sender, save_notifications_opts)
+                })
+                .await;
+        }
+        if !opts.watch.no_file_watcher {
+            manager
+                .spawn("run_watcher", move |handle| {
+                    run_watcher(handle, ghci_sender, watcher_opts)
+                })
+                .await;
+        }
+    }
     let ret = manager.wait_for_shutdown().await;
+    // Run these after the `ghci` session has been shut down, so they don't race with it.
+    let hooks_ret = opts
+        .hooks
+        .run_shutdown_hooks(opts.error_file.as_deref())
+        .await;
     tracing::debug!("main() finished");
-    ret
+    ret.and(hooks_ret)
 }
@@ -1,12 +1,19 @@
 use std::fmt::Display;
 use std::fmt::Write;
 use std::process::ExitStatus;
+use std::process::Output;
 use std::process::Stdio;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
+use command_group::AsyncCommandGroup;
 use eyre::eyre;
 use eyre::Context;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::instrument;
 use tracing::Instrument;
 use winnow::combinator::opt;
@@ -16,12 +23,17 @@ use winnow::Parser;
 
 use crate::clonable_command::ClonableCommand;
 use crate::command_ext::CommandExt;
+use crate::ghci::parse::diagnostic_format_prefix;
+use crate::ghci::parse::DiagnosticFormat;
+use crate::placeholders::Placeholders;
 
 /// A shell command which may optionally be run asynchronously.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MaybeAsyncCommand {
     /// Should this command be run asynchronously?
     pub is_async: bool,
+    /// The format of diagnostics printed by this command, if any.
+    pub diagnostics: Option<DiagnosticFormat>,
     /// The contained command.
     pub command: ClonableCommand,
 }
@@ -36,46 +48,102 @@ impl FromStr for MaybeAsyncCommand {
     type Err = eyre::Report;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_maybe_async_command
+        let command = parse_maybe_async_command
             .parse(s)
-            .map_err(|err| eyre!("{err}"))
+            .map_err(|err| eyre!("{err}"))?;
+        if let (true, Some(format)) = (command.is_async, command.diagnostics) {
+            return Err(eyre!(
+                "`async:` commands can't report diagnostics; remove `async:` or `{format}:`"
+            ));
+        }
+        Ok(command)
     }
 }
 
 fn parse_maybe_async_command(input: &mut &str) -> PResult<MaybeAsyncCommand> {
     let is_async = opt("async:").parse_next(input)?.is_some();
 
+    let diagnostics = opt(diagnostic_format_prefix).parse_next(input)?;
+
     let command = rest.parse_to().parse_next(input)?;
 
-    Ok(MaybeAsyncCommand { is_async, command })
+    Ok(MaybeAsyncCommand {
+        is_async,
+        diagnostics,
+        command,
+    })
 }
 
 impl MaybeAsyncCommand {
+    /// Expand [`Placeholders`] like `{changed_files}` in this command.
+    pub fn expand(&self, placeholders: &Placeholders) -> Self {
+        Self {
+            command: self.command.expand(placeholders),
+            ..self.clone()
+        }
+    }
+
+    /// Run this command, killing its process group if it runs longer than `timeout`.
     #[instrument(skip(self), fields(%self), level = "debug")]
-    pub async fn status(&self) -> MaybeAsyncCommandStatus {
+    pub async fn status(&self, timeout: Option<Duration>) -> MaybeAsyncCommandStatus {
         let program = self.command.program.to_string_lossy().into_owned();
         let mut command = self.command.as_tokio();
         let command_formatted = self.display();
+        let cancellation = CancellationToken::new();
+        let task_cancellation = cancellation.clone();
         let join_handle = tokio::task::spawn(
             async move {
                 tracing::info!("$ {command_formatted}");
-                let output = command
+                let start_instant = Instant::now();
+                // We spawn the command in its own process group so that we can kill it (and
+                // anything it spawns) if it times out or is cancelled.
+                let mut child = command
+                    .stdin(Stdio::null())
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
-                    .output()
-                    .await
+                    .group_spawn()
                     .wrap_err_with(|| format!("Failed to execute `{command_formatted}`"))?;
 
-                let status = output.status;
+                let stdout = read_pipe(child.inner().stdout.take());
+                let stderr = read_pipe(child.inner().stderr.take());
+
+                // NB: We wait on the inner child rather than the process group, because
+                // `AsyncGroupChild::wait` isn't cancel-safe.
+                let outcome = tokio::select! {
+                    status = child.inner().wait() => Ok(status),
+                    _ = sleep_until_timeout(timeout) => Err(Killed::TimedOut),
+                    _ = task_cancellation.cancelled() => Err(Killed::Cancelled),
+                };
+                let elapsed = start_instant.elapsed();
+                let (status, killed) = match outcome {
+                    Ok(status) => (status, None),
+                    Err(killed) => {
+                        child.kill().wrap_err_with(|| {
+                            format!("Failed to kill `{command_formatted}` after it {killed}")
+                        })?;
+                        (child.inner().wait().await, Some(killed))
+                    }
+                };
+                let status =
+                    status.wrap_err_with(|| format!("Failed to wait for `{command_formatted}`"))?;
 
                 let mut message = shell_words::quote(&program).into_owned();
                 message.push(' ');
-                if status.success() {
+                if let Some(killed) = killed {
+                    write!(message, "{killed} after {elapsed:.2?}")
+                        .expect("Writing to a `String` never fails");
+                } else if status.success() {
                     message.push_str("finished successfully");
                 } else {
                     write!(message, "failed: {status}").expect("Writing to a `String` never fails");
                 }
 
+                let output = Output {
+                    status,
+                    stdout: stdout.await??,
+                    stderr: stderr.await??,
+                };
+
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 let stdout = stdout.trim();
                 if !stdout.is_empty() {
@@ -90,56 +158,162 @@ impl MaybeAsyncCommand {
                         .expect("Writing to a `String` never fails");
                 }
 
-                if status.success() {
-                    tracing::debug!("{message}");
-                } else {
-                    tracing::error!("{message}");
+                match killed {
+                    Some(Killed::TimedOut) => tracing::error!(timed_out = true, "{message}"),
+                    Some(Killed::Cancelled) => tracing::debug!(cancelled = true, "{message}"),
+                    None if status.success() => tracing::debug!("{message}"),
+                    None => tracing::error!("{message}"),
                 }
 
-                Ok(status)
+                Ok(CommandOutput {
+                    output,
+                    timed_out: matches!(killed, Some(Killed::TimedOut)).then_some(elapsed),
+                })
             }
             .instrument(tracing::debug_span!("status").or_current()),
         );
 
         if self.is_async {
-            MaybeAsyncCommandStatus::Async(join_handle)
+            MaybeAsyncCommandStatus::Async(AsyncCommandHandle {
+                command: self.display(),
+                cancellation,
+                join_handle,
+            })
         } else {
             let command_formatted = self.display();
+            // If we're cancelled while waiting (like when a reload is aborted), kill the command
+            // rather than leaving it running in the background.
+            let cancel_on_drop = cancellation.drop_guard();
             let status = join_handle
                 .await
                 .wrap_err_with(|| format!("Panicked while executing `{command_formatted}`"))
                 .and_then(std::convert::identity);
+            cancel_on_drop.disarm();
             MaybeAsyncCommandStatus::Sync(status)
         }
     }
 
     /// Run this command.
     ///
-    /// If it's a synchronous command, report its status. Otherwise, add the [`JoinHandle`] for its
-    /// task to the given list of handles.
+    /// If it's a synchronous command, return its output. Otherwise, add an
+    /// [`AsyncCommandHandle`] for its task to the given list of handles.
+    ///
+    /// If a `timeout` is given, the command's process group is killed if it runs longer than
+    /// that, which is reported in [`CommandOutput::timed_out`].
     pub async fn run_on(
         &self,
-        handles: &mut Vec<JoinHandle<eyre::Result<ExitStatus>>>,
-    ) -> eyre::Result<()> {
-        match self.status().await {
+        handles: &mut Vec<AsyncCommandHandle>,
+        timeout: Option<Duration>,
+    ) -> eyre::Result<Option<CommandOutput>> {
+        match self.status(timeout).await {
             MaybeAsyncCommandStatus::Sync(result) => {
                 // If we failed to execute the program, that's an actual error, but if the
-                // program failed on its own, we'll log and move on.
-                result?;
+                // program failed on its own (or timed out), we'll log and move on.
+                Ok(Some(result?))
             }
-            MaybeAsyncCommandStatus::Async(join_handle) => {
-                // If the program is running asynchronously, we'll store the `JoinHandle`
-                // so we don't kill it and so we can log when it completes.
-                handles.push(join_handle);
+            MaybeAsyncCommandStatus::Async(handle) => {
+                // If the program is running asynchronously, we'll store the handle so we don't
+                // kill it, so we can log when it completes, and so we can cancel it later.
+                handles.push(handle);
+                Ok(None)
             }
         }
-        Ok(())
     }
 }
 
+/// The output of a command which finished running.
+#[derive(Debug)]
+pub struct CommandOutput {
+    /// The command's exit status and output.
+    pub output: Output,
+    /// If the command was killed for running longer than its timeout, how long it ran.
+    pub timed_out: Option<Duration>,
+}
+
+/// Why a command's process group was killed.
+#[derive(Debug, Clone, Copy)]
+enum Killed {
+    /// The command ran longer than its timeout.
+    TimedOut,
+    /// The command was cancelled through its [`AsyncCommandHandle`].
+    Cancelled,
+}
+
+impl Display for Killed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Killed::TimedOut => write!(f, "timed out"),
+            Killed::Cancelled => write!(f, "was cancelled"),
+        }
+    }
+}
+
+/// Sleep for the given timeout, or forever if there's no timeout.
+async fn sleep_until_timeout(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Read the given pipe (if any) to the end in a background task.
+///
+/// Reading in the background lets us wait for (or kill) the process while its output is
+/// collected.
+fn read_pipe(
+    pipe: Option<impl AsyncRead + Unpin + Send + 'static>,
+) -> JoinHandle<std::io::Result<Vec<u8>>> {
+    tokio::task::spawn(async move {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buffer).await?;
+        }
+        Ok(buffer)
+    })
+}
+
 pub enum MaybeAsyncCommandStatus {
-    Sync(eyre::Result<ExitStatus>),
-    Async(JoinHandle<eyre::Result<ExitStatus>>),
+    Sync(eyre::Result<CommandOutput>),
+    Async(AsyncCommandHandle),
+}
+
+/// A handle to an `async:` command running in the background.
+#[derive(Debug)]
+pub struct AsyncCommandHandle {
+    /// The command being run, formatted for display.
+    command: String,
+    /// Cancels the command, killing its process group.
+    cancellation: CancellationToken,
+    /// The task running the command.
+    join_handle: JoinHandle<eyre::Result<CommandOutput>>,
+}
+
+impl AsyncCommandHandle {
+    /// Has the command finished running?
+    pub fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    /// Kill the command's process group if it's still running.
+    ///
+    /// The command's output is logged when it exits.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Wait for the command to finish.
+    pub async fn wait(self) -> eyre::Result<ExitStatus> {
+        self.join_handle
+            .await
+            .wrap_err_with(|| format!("Failed to join `{}`", self.command))?
+            .map(|output| output.output.status)
+    }
+}
+
+impl Display for AsyncCommandHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.command)
+    }
 }
 
 impl CommandExt for MaybeAsyncCommand {
@@ -160,6 +334,7 @@ mod tests {
                 .unwrap(),
             MaybeAsyncCommand {
                 is_async: false,
+                diagnostics: None,
                 command: ClonableCommand::new("puppy")
                     .args(["--flavor", "sammy", "--eyes", "brown"])
             }
@@ -171,9 +346,58 @@ mod tests {
                 .unwrap(),
             MaybeAsyncCommand {
                 is_async: true,
+                diagnostics: None,
                 command: ClonableCommand::new("puppy")
                     .args(["--flavor", "sammy", "--eyes", "brown"])
             }
         );
+
+        assert_eq!(
+            "hlint-json:hlint --json src"
+                .parse::<MaybeAsyncCommand>()
+                .unwrap(),
+            MaybeAsyncCommand {
+                is_async: false,
+                diagnostics: Some(DiagnosticFormat::HlintJson),
+                command: ClonableCommand::new("hlint").args(["--json", "src"])
+            }
+        );
+
+        assert!("async:ghc-diagnostics:my-linter"
+            .parse::<MaybeAsyncCommand>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_command() {
+        let command = "sh -c 'sleep 60; echo done'"
+            .parse::<MaybeAsyncCommand>()
+            .unwrap();
+        let start = Instant::now();
+        let output = match command.status(Some(Duration::from_millis(100))).await {
+            MaybeAsyncCommandStatus::Sync(output) => output.unwrap(),
+            MaybeAsyncCommandStatus::Async(_) => unreachable!(),
+        };
+        assert!(!output.output.status.success());
+        assert!(output.timed_out.is_some());
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_kills_async_command() {
+        let command = "async:sh -c 'sleep 60; echo done'"
+            .parse::<MaybeAsyncCommand>()
+            .unwrap();
+        let mut handles = Vec::new();
+        command.run_on(&mut handles, None).await.unwrap();
+        assert_eq!(handles.len(), 1);
+        let handle = handles.pop().unwrap();
+        assert!(!handle.is_finished());
+
+        let start = Instant::now();
+        handle.cancel();
+        let status = handle.wait().await.unwrap();
+        assert!(!status.success());
+        assert!(start.elapsed() < Duration::from_secs(30));
     }
 }
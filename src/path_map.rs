@@ -0,0 +1,206 @@
+//! Mapping paths between `ghci` and `ghciwatch`, for when `ghci` runs in a container.
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use clap::builder::StringValueParser;
+use clap::builder::TypedValueParser;
+use clap::builder::ValueParserFactory;
+use eyre::eyre;
+
+use crate::normal_path::NormalPath;
+
+/// A mapping from a directory as `ghci` sees it to the same directory as `ghciwatch` sees it.
+///
+/// Parsed from `CONTAINER=HOST` arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMapping {
+    /// The directory as `ghci` sees it, like `/workspace`.
+    ghci: Utf8PathBuf,
+    /// The directory as `ghciwatch` sees it, like `/home/me/code/app`.
+    host: Utf8PathBuf,
+}
+
+impl PathMapping {
+    /// Parse a mapping from a `CONTAINER=HOST` string. Relative host paths are resolved from the
+    /// current directory.
+    pub fn new(mapping: &str) -> eyre::Result<Self> {
+        let (ghci, host) = mapping
+            .split_once('=')
+            .ok_or_else(|| eyre!("Path mappings must be formatted like `CONTAINER=HOST`"))?;
+        if ghci.is_empty() || host.is_empty() {
+            return Err(eyre!(
+                "Path mappings must be formatted like `CONTAINER=HOST`"
+            ));
+        }
+        Ok(Self {
+            ghci: ghci.into(),
+            host: NormalPath::from_cwd(host)?.into_absolute(),
+        })
+    }
+}
+
+/// Mappings between paths as `ghci` sees them and paths as `ghciwatch` sees them.
+///
+/// Paths `ghci` prints (like in `:show paths` output and compiler diagnostics) are mapped to host
+/// paths when they're parsed, and paths `ghciwatch` sends to `ghci` (like in `:add` commands) are
+/// mapped back.
+#[derive(Debug, Clone, Default)]
+pub struct PathMap {
+    mappings: Vec<PathMapping>,
+}
+
+impl PathMap {
+    /// Construct a path map from the given mappings.
+    pub fn new(mappings: Vec<PathMapping>) -> Self {
+        Self { mappings }
+    }
+
+    /// Map a path as `ghci` sees it to a path as `ghciwatch` sees it.
+    ///
+    /// Relative paths and paths outside of the mapped directories are returned unchanged. If
+    /// multiple mappings apply, the one with the longest `ghci` path is used.
+    pub fn to_host(&self, path: &Utf8Path) -> Utf8PathBuf {
+        map_path(
+            path,
+            self.mappings
+                .iter()
+                .map(|mapping| (&mapping.ghci, &mapping.host)),
+        )
+        .unwrap_or_else(|| path.to_owned())
+    }
+
+    /// Map an absolute path as `ghciwatch` sees it to a path as `ghci` sees it.
+    ///
+    /// Returns `None` for paths outside of the mapped directories. If multiple mappings apply,
+    /// the one with the longest host path is used.
+    pub fn to_ghci(&self, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        map_path(
+            path,
+            self.mappings
+                .iter()
+                .map(|mapping| (&mapping.host, &mapping.ghci)),
+        )
+    }
+}
+
+/// Map a path with the `(from, to)` mapping with the longest matching `from` path.
+fn map_path<'a>(
+    path: &Utf8Path,
+    mappings: impl Iterator<Item = (&'a Utf8PathBuf, &'a Utf8PathBuf)>,
+) -> Option<Utf8PathBuf> {
+    mappings
+        .filter_map(|(from, to)| {
+            path.strip_prefix(from)
+                .ok()
+                .map(|suffix| (from, to, suffix))
+        })
+        .max_by_key(|(from, _, _)| from.as_str().len())
+        .map(|(_, to, suffix)| {
+            if suffix.as_str().is_empty() {
+                to.clone()
+            } else {
+                to.join(suffix)
+            }
+        })
+}
+
+/// [`clap`] parser for [`PathMapping`] values.
+#[derive(Default, Clone)]
+pub struct PathMappingValueParser {
+    inner: StringValueParser,
+}
+
+impl TypedValueParser for PathMappingValueParser {
+    type Value = PathMapping;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.inner.parse_ref(cmd, arg, value).and_then(|mapping| {
+            PathMapping::new(&mapping)
+                .map_err(|err| crate::clap::value_validation_error(arg, &mapping, format!("{err}")))
+        })
+    }
+}
+
+impl ValueParserFactory for PathMapping {
+    type Parser = PathMappingValueParser;
+
+    fn value_parser() -> Self::Parser {
+        Self::Parser::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_path_mapping() {
+        assert_eq!(
+            PathMapping::new("/workspace=/home/me/code/app").unwrap(),
+            PathMapping {
+                ghci: "/workspace".into(),
+                host: "/home/me/code/app".into(),
+            }
+        );
+        assert!(PathMapping::new("/workspace").is_err());
+        assert!(PathMapping::new("=/home/me/code/app").is_err());
+    }
+
+    #[test]
+    fn test_path_map_to_host() {
+        let path_map = PathMap::new(vec![
+            PathMapping::new("/workspace=/home/me/code/app").unwrap(),
+            PathMapping::new("/workspace/vendor=/home/me/code/vendor").unwrap(),
+        ]);
+
+        assert_eq!(
+            path_map.to_host(Utf8Path::new("/workspace")),
+            Utf8PathBuf::from("/home/me/code/app")
+        );
+        assert_eq!(
+            path_map.to_host(Utf8Path::new("/workspace/src/MyLib.hs")),
+            Utf8PathBuf::from("/home/me/code/app/src/MyLib.hs")
+        );
+        assert_eq!(
+            path_map.to_host(Utf8Path::new("/workspace/vendor/src/Dep.hs")),
+            Utf8PathBuf::from("/home/me/code/vendor/src/Dep.hs")
+        );
+        // Only whole path components are mapped.
+        assert_eq!(
+            path_map.to_host(Utf8Path::new("/workspace-2/src/MyLib.hs")),
+            Utf8PathBuf::from("/workspace-2/src/MyLib.hs")
+        );
+        assert_eq!(
+            path_map.to_host(Utf8Path::new("src/MyLib.hs")),
+            Utf8PathBuf::from("src/MyLib.hs")
+        );
+    }
+
+    #[test]
+    fn test_path_map_to_ghci() {
+        let path_map = PathMap::new(vec![
+            PathMapping::new("/workspace=/home/me/code/app").unwrap(),
+            PathMapping::new("/vendor=/home/me/code/app/vendor").unwrap(),
+        ]);
+
+        assert_eq!(
+            path_map.to_ghci(Utf8Path::new("/home/me/code/app/src/MyLib.hs")),
+            Some(Utf8PathBuf::from("/workspace/src/MyLib.hs"))
+        );
+        assert_eq!(
+            path_map.to_ghci(Utf8Path::new("/home/me/code/app/vendor/src/Dep.hs")),
+            Some(Utf8PathBuf::from("/vendor/src/Dep.hs"))
+        );
+        assert_eq!(
+            path_map.to_ghci(Utf8Path::new("/home/me/code/other/src/MyLib.hs")),
+            None
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Placeholders like `{changed_files}` in lifecycle hook commands.
+
+use std::ffi::OsString;
+
+use crate::hooks::LifecycleEvent;
+
+/// Values for the placeholders in lifecycle hook commands.
+///
+/// Placeholders are written in braces, like `{changed_files}`, and expand to their values,
+/// separated by spaces. See [`Placeholders::expand_haskell`] and [`Placeholders::expand_args`]
+/// for how values are quoted by default; a format suffix like `{changed_files:shell}` overrides
+/// this. Braces which don't contain a known placeholder name (and format) are left
+/// alone, so Haskell code like `Foo { bar = 1 }` is unaffected.
+#[derive(Debug, Clone)]
+pub struct Placeholders {
+    /// The event the hook is being run for, like `after-reload`; `{event}`.
+    pub event: LifecycleEvent,
+    /// Paths changed by the current reload or restart; `{changed_files}`.
+    pub changed_files: Vec<String>,
+    /// Names of modules changed by the current reload or restart; `{changed_modules}`.
+    pub changed_modules: Vec<String>,
+    /// The path of the error log, if any; `{error_file}`.
+    pub error_file: Option<String>,
+}
+
+/// How a placeholder's values are written, chosen with a suffix like `{changed_files:shell}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Shell-quoted and separated by spaces, for shell scripts like `sh -c '...'` or `:! ...`;
+    /// `:shell`.
+    Shell,
+    /// Haskell string literals separated by commas, for list literals like
+    /// `[{changed_modules:list}]`; `:list`.
+    List,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "shell" => Some(Self::Shell),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+
+    fn join(self, values: &[String]) -> String {
+        match self {
+            Format::Shell => shell_words::join(values),
+            Format::List => values
+                .iter()
+                .map(|value| format!("\"{}\"", escape_haskell_string(value)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+impl Placeholders {
+    /// Create a new set of placeholders for the given event, with no changed files or error log.
+    pub fn new(event: LifecycleEvent) -> Self {
+        Self {
+            event,
+            changed_files: Vec::new(),
+            changed_modules: Vec::new(),
+            error_file: None,
+        }
+    }
+
+    /// Get the values for the placeholder with the given name, if it exists.
+    fn get(&self, name: &str) -> Option<Vec<String>> {
+        match name {
+            "event" => Some(vec![self.event.to_string()]),
+            "changed_files" => Some(self.changed_files.clone()),
+            "changed_modules" => Some(self.changed_modules.clone()),
+            "error_file" => Some(self.error_file.iter().cloned().collect()),
+            _ => None,
+        }
+    }
+
+    /// Get the values and format for the contents of a placeholder's braces, like
+    /// `changed_files` or `changed_files:shell`, if it's a known placeholder.
+    fn get_with_format(&self, placeholder: &str) -> Option<(Vec<String>, Option<Format>)> {
+        match placeholder.split_once(':') {
+            Some((name, format)) => Some((self.get(name)?, Some(Format::from_name(format)?))),
+            None => Some((self.get(placeholder)?, None)),
+        }
+    }
+
+    /// Expand the placeholders in the given string, escaping their values for use in a Haskell
+    /// string literal, like `"{changed_files}"`, unless another format is given.
+    ///
+    /// Values are escaped like Haskell's `show`, without the surrounding quotes.
+    pub fn expand_haskell(&self, input: &str) -> String {
+        self.expand_with(input, |values| {
+            values
+                .iter()
+                .map(|value| escape_haskell_string(value))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+    }
+
+    /// Expand the placeholders in the given string, separating their values with spaces but
+    /// leaving them unquoted unless another format is given.
+    pub fn expand_unquoted(&self, input: &str) -> String {
+        self.expand_with(input, |values| values.join(" "))
+    }
+
+    /// Expand the placeholders in the given string, joining their values with `join` unless a
+    /// format is given.
+    fn expand_with(&self, input: &str, join: impl Fn(&[String]) -> String) -> String {
+        let mut ret = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find('{') {
+            ret.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let values = rest
+                .find('}')
+                .and_then(|end| Some((end, self.get_with_format(&rest[1..end])?)));
+
+            match values {
+                Some((end, (values, format))) => {
+                    match format {
+                        Some(format) => ret.push_str(&format.join(&values)),
+                        None => ret.push_str(&join(&values)),
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    ret.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        ret.push_str(rest);
+        ret
+    }
+
+    /// Expand the placeholders in the given command-line arguments.
+    ///
+    /// Arguments which consist of a single placeholder, like `{changed_files}`, are replaced with
+    /// one argument for each value, so that they don't need to be split again. Arguments are
+    /// passed to programs directly rather than through a shell, so placeholders in other arguments
+    /// expand to values separated by spaces without any quoting, unless another format is given;
+    /// scripts for a shell should use the `:shell` format, like
+    /// `sh -c 'hlint {changed_files:shell}'`.
+    pub fn expand_args(&self, args: &[OsString]) -> Vec<OsString> {
+        let mut ret = Vec::with_capacity(args.len());
+        for arg in args {
+            let Some(arg_str) = arg.to_str() else {
+                ret.push(arg.clone());
+                continue;
+            };
+
+            let values = arg_str
+                .strip_prefix('{')
+                .and_then(|name| name.strip_suffix('}'))
+                .and_then(|name| self.get(name));
+
+            match values {
+                Some(values) => ret.extend(values.into_iter().map(OsString::from)),
+                None => ret.push(self.expand_unquoted(arg_str).into()),
+            }
+        }
+        ret
+    }
+}
+
+/// Escape a string like Haskell's `showLitString`, so that it can be written between double
+/// quotes in a Haskell string literal.
+fn escape_haskell_string(value: &str) -> String {
+    const ASCII_NAMES: [&str; 32] = [
+        "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "a", "b", "t", "n", "v", "f", "r", "SO",
+        "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+        "FS", "GS", "RS", "US",
+    ];
+
+    let mut ret = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\x7f' => ret.push_str("\\DEL"),
+            ' '..='~' => ret.push(c),
+            '\0'..='\x1f' => {
+                ret.push('\\');
+                ret.push_str(ASCII_NAMES[c as usize]);
+                // `\SOH` is a different character than `\SO` followed by `H`.
+                if c == '\x0e' && chars.peek() == Some(&'H') {
+                    ret.push_str("\\&");
+                }
+            }
+            _ => {
+                ret.push_str(&format!("\\{}", c as u32));
+                // Numeric escapes would otherwise absorb following digits.
+                if chars.peek().is_some_and(char::is_ascii_digit) {
+                    ret.push_str("\\&");
+                }
+            }
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::hooks::When;
+
+    fn placeholders() -> Placeholders {
+        Placeholders {
+            changed_files: vec!["src/My Lib.hs".into(), "src/MyModule.hs".into()],
+            changed_modules: vec!["MyLib".into(), "MyModule".into()],
+            error_file: Some("ghcid.txt".into()),
+            ..Placeholders::new(LifecycleEvent::Reload(When::After))
+        }
+    }
+
+    #[test]
+    fn test_expand_unquoted() {
+        let placeholders = placeholders();
+        assert_eq!(
+            placeholders.expand_unquoted("hlint {changed_files}"),
+            "hlint src/My Lib.hs src/MyModule.hs"
+        );
+        assert_eq!(
+            placeholders.expand_unquoted("{event}: {error_file}"),
+            "after-reload: ghcid.txt"
+        );
+        assert_eq!(
+            Placeholders::new(LifecycleEvent::Startup(When::After))
+                .expand_unquoted("cat {error_file}"),
+            "cat "
+        );
+    }
+
+    #[test]
+    fn test_expand_formats() {
+        let placeholders = placeholders();
+        assert_eq!(
+            placeholders.expand_unquoted("hlint {changed_files:shell}"),
+            "hlint 'src/My Lib.hs' src/MyModule.hs"
+        );
+        assert_eq!(
+            placeholders.expand_haskell(":! hlint {changed_files:shell}"),
+            ":! hlint 'src/My Lib.hs' src/MyModule.hs"
+        );
+        assert_eq!(
+            placeholders.expand_haskell("print [{changed_modules:list}]"),
+            r#"print ["MyLib", "MyModule"]"#
+        );
+        assert_eq!(
+            Placeholders::new(LifecycleEvent::Startup(When::After))
+                .expand_haskell("print [{changed_modules:list}]"),
+            "print []"
+        );
+    }
+
+    #[test]
+    fn test_expand_leaves_other_braces() {
+        let placeholders = placeholders();
+        assert_eq!(
+            placeholders.expand_haskell("print Foo { bar = 1 } {}{ {changed_modules"),
+            "print Foo { bar = 1 } {}{ {changed_modules"
+        );
+        assert_eq!(
+            placeholders.expand_haskell("{changed_modules:show} {{event}}"),
+            "{changed_modules:show} {after-reload}"
+        );
+    }
+
+    #[test]
+    fn test_expand_haskell() {
+        let placeholders = Placeholders {
+            changed_files: vec![
+                r#"src/My "Lib".hs"#.into(),
+                r"src\MyModule.hs".into(),
+                "src/\u{e9}1.hs".into(),
+                "src/\t\u{e}H.hs".into(),
+            ],
+            ..placeholders()
+        };
+        assert_eq!(
+            placeholders.expand_haskell(r#"print "{changed_files}""#),
+            r#"print "src/My \"Lib\".hs src\\MyModule.hs src/\233\&1.hs src/\t\SO\&H.hs""#
+        );
+        assert_eq!(
+            placeholders.expand_haskell(r#"print [{changed_files:list}]"#),
+            r#"print ["src/My \"Lib\".hs", "src\\MyModule.hs", "src/\233\&1.hs", "src/\t\SO\&H.hs"]"#
+        );
+    }
+
+    #[test]
+    fn test_expand_args() {
+        let placeholders = placeholders();
+        assert_eq!(
+            placeholders.expand_args(&[
+                "--files".into(),
+                "{changed_files}".into(),
+                "--event={event}".into(),
+                "--files={changed_files}".into(),
+            ]),
+            vec![
+                OsString::from("--files"),
+                "src/My Lib.hs".into(),
+                "src/MyModule.hs".into(),
+                "--event=after-reload".into(),
+                "--files=src/My Lib.hs src/MyModule.hs".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_args_shell_script() {
+        let placeholders = placeholders();
+        assert_eq!(
+            placeholders.expand_args(&[
+                "-ec".into(),
+                "hlint {changed_files:shell}".into(),
+                "{changed_files:shell}".into(),
+            ]),
+            vec![
+                OsString::from("-ec"),
+                "hlint 'src/My Lib.hs' src/MyModule.hs".into(),
+                "'src/My Lib.hs' src/MyModule.hs".into(),
+            ]
+        );
+        // Arguments after `-c` aren't necessarily shell scripts.
+        assert_eq!(
+            placeholders.expand_args(&["-c".into(), "core.pager={error_file}".into()]),
+            vec![OsString::from("-c"), "core.pager=ghcid.txt".into()]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Receiving file save notifications from editors, as an alternative to watching the filesystem.
+
+use std::collections::BTreeSet;
+use std::os::unix::fs::FileTypeExt;
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use eyre::eyre;
+use eyre::WrapErr;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tokio::net::UnixListener;
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::instrument;
+
+use crate::cli::Opts;
+use crate::event_filter::FileEvent;
+use crate::ghci::manager::WatcherEvent;
+use crate::normal_path::NormalPath;
+use crate::shutdown::ShutdownHandle;
+
+/// Options for [`run_save_notifications`].
+pub struct SaveNotificationsOpts {
+    /// The path of the Unix socket to listen on.
+    pub socket: Utf8PathBuf,
+    /// Wait this long after a notification for more notifications before reloading.
+    pub debounce: Duration,
+}
+
+impl SaveNotificationsOpts {
+    /// Construct options for [`run_save_notifications`] from parsed command-line interface
+    /// arguments as [`Opts`], if `--save-socket` is given.
+    pub fn from_cli(opts: &Opts) -> Option<Self> {
+        opts.watch.save_socket.as_ref().map(|socket| Self {
+            socket: socket.clone(),
+            debounce: opts.watch.debounce,
+        })
+    }
+}
+
+/// Listen on a Unix socket for file save notifications from editors and send reload events to
+/// the `ghci` session.
+///
+/// Each line written to the socket is the path of a file which was saved (or deleted), either
+/// absolute or relative to `ghciwatch`'s working directory.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_save_notifications(
+    mut handle: ShutdownHandle,
+    ghci_sender: mpsc::Sender<WatcherEvent>,
+    opts: SaveNotificationsOpts,
+) -> eyre::Result<()> {
+    remove_stale_socket(&opts.socket)?;
+    let listener = UnixListener::bind(&opts.socket)
+        .wrap_err_with(|| format!("Failed to listen on {}", opts.socket))?;
+    tracing::info!(socket = %opts.socket, "Listening for save notifications");
+
+    let (path_sender, mut path_receiver) = mpsc::channel(32);
+    let mut events = BTreeSet::new();
+    let mut deadline = None;
+
+    let ret = loop {
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break Ok(());
+            }
+            connection = listener.accept() => {
+                match connection {
+                    Ok((stream, _address)) => {
+                        tokio::task::spawn(read_notifications(stream, path_sender.clone()));
+                    }
+                    Err(err) => {
+                        tracing::warn!("Failed to accept save notification connection: {err}");
+                    }
+                }
+            }
+            Some(path) = path_receiver.recv() => {
+                match file_event_for_path(&path) {
+                    Ok(event) => {
+                        tracing::debug!(?event, "Got save notification");
+                        events.insert(event);
+                        deadline = Some(Instant::now() + opts.debounce);
+                    }
+                    Err(err) => {
+                        tracing::warn!("Invalid save notification {path:?}: {err}");
+                    }
+                }
+            }
+            _ = sleep_until(deadline) => {
+                deadline = None;
+                let events = std::mem::take(&mut events);
+                tracing::debug!(?events, "Processed save notifications");
+                if let Err(err) = ghci_sender.send(WatcherEvent::Reload { events }).await {
+                    break Err(err.into());
+                }
+            }
+        }
+    };
+
+    if let Err(err) = std::fs::remove_file(&opts.socket) {
+        tracing::debug!(socket = %opts.socket, "Failed to remove socket: {err}");
+    }
+
+    ret
+}
+
+/// Remove a socket left over from a previous `ghciwatch` session, so that we can listen on its
+/// path. Other files are left alone.
+fn remove_stale_socket(socket: &Utf8PathBuf) -> eyre::Result<()> {
+    match socket.symlink_metadata() {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(socket)
+            .wrap_err_with(|| format!("Failed to remove stale socket {socket}")),
+        Ok(_) => Err(eyre!("Cannot listen on {socket}: File exists")),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Read newline-separated paths from a connection until it's closed.
+async fn read_notifications(stream: UnixStream, path_sender: mpsc::Sender<String>) {
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                if path_sender.send(line.to_owned()).await.is_err() {
+                    // We're shutting down.
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                tracing::debug!("Failed to read save notification: {err}");
+                break;
+            }
+        }
+    }
+}
+
+/// Convert a notification for the given path into a [`FileEvent`].
+fn file_event_for_path(path: &str) -> eyre::Result<FileEvent> {
+    let path = NormalPath::from_cwd(path)?.into_absolute();
+    Ok(if path.exists() {
+        FileEvent::Modify(path)
+    } else {
+        FileEvent::Remove(path)
+    })
+}
+
+/// Sleep until the deadline, if there is one.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -1,5 +1,11 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use eyre::eyre;
 use notify_debouncer_full::notify;
 use notify_debouncer_full::notify::PollWatcher;
@@ -11,12 +17,19 @@ use notify_debouncer_full::Debouncer;
 use notify_debouncer_full::FileIdMap;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tokio::task::block_in_place;
 use tracing::instrument;
 
+use crate::bulk_change::BulkChange;
+use crate::bulk_change::BulkChangeOpts;
+use crate::bulk_change::GitDir;
 use crate::cli::Opts;
+use crate::content_hashes::ContentHashes;
 use crate::event_filter::file_events_from_action;
+use crate::event_filter::FileEvent;
 use crate::ghci::manager::WatcherEvent;
+use crate::ignore::IgnoreFiles;
 use crate::normal_path::NormalPath;
 use crate::shutdown::ShutdownHandle;
 
@@ -29,6 +42,21 @@ pub struct WatcherOpts {
     pub debounce: Duration,
     /// If given, use the polling file watcher with the given duration as the poll interval.
     pub poll: Option<Duration>,
+    /// If given, also watch the paths sent here by the `ghci` session, like the paths derived
+    /// from `ghci`'s module search paths, updating them as they change.
+    pub search_paths: Option<watch::Receiver<BTreeSet<Utf8PathBuf>>>,
+    /// Drop events for Haskell source files whose contents haven't changed.
+    pub skip_unchanged: bool,
+    /// Hold file events during bulk changes and handle them all at once.
+    pub hold_bulk_changes: bool,
+    /// A batch of at least this many file events is considered a bulk change.
+    pub bulk_change_threshold: usize,
+    /// A bulk change is finished once no file events have arrived for this long.
+    pub bulk_change_quiet_period: Duration,
+    /// Watch the Git directory for activity which starts a bulk change.
+    pub git_detection: bool,
+    /// Ignore files to respect when looking for files in new directories.
+    pub ignore_files: Option<IgnoreFiles>,
 }
 
 impl WatcherOpts {
@@ -41,6 +69,13 @@ impl WatcherOpts {
             watch: opts.watch.paths.clone(),
             debounce: opts.watch.debounce,
             poll: opts.watch.poll,
+            search_paths: None,
+            skip_unchanged: opts.watch.skip_unchanged,
+            hold_bulk_changes: opts.watch.hold_bulk_changes,
+            bulk_change_threshold: opts.watch.bulk_change_threshold,
+            bulk_change_quiet_period: opts.watch.bulk_change_quiet_period,
+            git_detection: !opts.watch.no_git_detection,
+            ignore_files: opts.watch.ignore_files(),
         }
     }
 }
@@ -70,10 +105,36 @@ async fn run_debouncer<T: notify::Watcher>(
         config = config.with_poll_interval(interval);
     }
 
+    let content_hashes = if opts.skip_unchanged {
+        Some(block_in_place(|| ContentHashes::from_paths(&opts.watch)))
+    } else {
+        None
+    };
+
+    let git_dir = if opts.hold_bulk_changes && opts.git_detection {
+        GitDir::find(&crate::current_dir()?)
+    } else {
+        None
+    };
+    let mut bulk_change = opts.hold_bulk_changes.then(|| {
+        BulkChange::new(BulkChangeOpts {
+            threshold: opts.bulk_change_threshold,
+            quiet_period: opts.bulk_change_quiet_period,
+            git_dir: git_dir.clone(),
+        })
+    });
+
+    let watched_files = WatchedFiles::new(&opts.watch);
+
+    let (batch_sender, mut batch_receiver) = mpsc::channel(32);
     let event_handler = EventHandler {
         handle: Handle::current(),
-        ghci_sender,
+        batch_sender,
         shutdown: handle.clone(),
+        content_hashes: content_hashes.clone(),
+        git_dir: git_dir.clone(),
+        ignore_files: opts.ignore_files.clone(),
+        watched_files: watched_files.clone(),
     };
 
     let cache = FileIdMap::new();
@@ -113,32 +174,263 @@ async fn run_debouncer<T: notify::Watcher>(
         }
     }
 
+    // Watch the Git directory (but not its contents) for activity like `git checkout`, unless
+    // it's already being watched.
+    if let Some(git_dir) = git_dir.filter(|git_dir| {
+        !opts
+            .watch
+            .iter()
+            .any(|path| git_dir.path().starts_with(path.absolute()))
+    }) {
+        match debouncer
+            .watcher()
+            .watch(git_dir.path(), RecursiveMode::NonRecursive)
+        {
+            Ok(()) => {
+                debouncer
+                    .cache()
+                    .add_root(git_dir.path(), RecursiveMode::NonRecursive);
+            }
+            Err(err) => {
+                tracing::debug!(path = %git_dir.path().display(), "Failed to watch Git directory: {err}");
+            }
+        }
+    }
+
     tracing::debug!("notify watcher started");
 
-    // Wait for a shutdown request, either from another subsystem or from an error in the handler.
-    let _ = handle.on_shutdown_requested().await;
+    let mut search_paths_receiver = opts.search_paths;
+    let ret = loop {
+        let events = tokio::select! {
+            // Wait for a shutdown request, either from another subsystem or from an error in the
+            // handler.
+            _ = handle.on_shutdown_requested() => {
+                break Ok(());
+            }
+            new_search_paths = next_search_paths(&mut search_paths_receiver) => {
+                update_search_paths(
+                    &mut debouncer,
+                    &opts.watch,
+                    &watched_files,
+                    content_hashes.as_ref(),
+                    &new_search_paths,
+                );
+                continue;
+            }
+            Some(batch) = batch_receiver.recv() => {
+                match &mut bulk_change {
+                    Some(bulk_change) => match bulk_change.add(batch.events, batch.git_activity) {
+                        Some(events) => events,
+                        None => continue,
+                    },
+                    None => batch.events,
+                }
+            }
+            events = bulk_change_finished(&mut bulk_change) => events,
+        };
+
+        if events.is_empty() {
+            tracing::debug!("No relevant file events");
+        } else {
+            tracing::debug!(?events, "Processed events");
+            if let Err(err) = ghci_sender.send(WatcherEvent::Reload { events }).await {
+                break Err(err.into());
+            }
+        }
+    };
 
     block_in_place(|| debouncer.stop());
 
-    Ok(())
+    ret
+}
+
+/// Wait for the bulk change in progress to finish, if bulk changes are held.
+async fn bulk_change_finished(bulk_change: &mut Option<BulkChange>) -> BTreeSet<FileEvent> {
+    match bulk_change {
+        Some(bulk_change) => bulk_change.finished().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait for `ghci`'s module search paths to change.
+async fn next_search_paths(
+    receiver: &mut Option<watch::Receiver<BTreeSet<Utf8PathBuf>>>,
+) -> BTreeSet<Utf8PathBuf> {
+    let Some(receiver) = receiver else {
+        return std::future::pending().await;
+    };
+
+    match receiver.changed().await {
+        Ok(()) => receiver.borrow_and_update().clone(),
+        // The `ghci` session is shutting down.
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// Watch paths derived from `ghci`'s module search paths, and stop watching the ones which aren't
+/// search paths anymore. Paths given with `--watch` are always watched.
+///
+/// Directories are watched recursively. Files (like `.cabal` files) are watched through their
+/// parent directories, because editors often save files by renaming a new file over them, which
+/// breaks watches on the files themselves.
+fn update_search_paths<T: notify::Watcher>(
+    debouncer: &mut Debouncer<T, FileIdMap>,
+    watch: &[NormalPath],
+    watched_files: &WatchedFiles,
+    content_hashes: Option<&ContentHashes>,
+    new: &BTreeSet<Utf8PathBuf>,
+) {
+    let is_watched = |path: &Utf8Path| watch.iter().any(|watch| path.starts_with(watch.absolute()));
+
+    let (new_dirs, new_files): (BTreeSet<_>, BTreeSet<_>) = new
+        .iter()
+        .filter(|path| !is_watched(path))
+        .cloned()
+        .partition(|path| path.is_dir());
+    let old_dirs = watched_files.dirs();
+    let old_file_dirs = file_dirs(&watched_files.files());
+    let new_file_dirs = file_dirs(&new_files);
+
+    let unwatch = old_dirs
+        .difference(&new_dirs)
+        .chain(old_file_dirs.difference(&new_file_dirs));
+    for path in unwatch {
+        tracing::debug!(%path, "No longer watching search path");
+        if let Err(err) = debouncer.watcher().unwatch(path.as_std_path()) {
+            tracing::debug!(%path, "Failed to unwatch path: {err}");
+        }
+        debouncer.cache().remove_root(path);
+    }
+
+    watched_files.set(new_dirs.clone(), new_files);
+
+    let watch = new_dirs
+        .difference(&old_dirs)
+        .map(|path| (path, RecursiveMode::Recursive))
+        .chain(
+            new_file_dirs
+                .difference(&old_file_dirs)
+                .map(|path| (path, RecursiveMode::NonRecursive)),
+        );
+    for (path, mode) in watch {
+        tracing::debug!(%path, "Watching search path");
+        if let Err(err) = debouncer.watcher().watch(path.as_std_path(), mode) {
+            tracing::warn!("Failed to watch {path}: {err}");
+            continue;
+        }
+        debouncer.cache().add_root(path, mode);
+    }
+
+    if let Some(content_hashes) = content_hashes {
+        block_in_place(|| {
+            content_hashes.hash_paths(new_dirs.difference(&old_dirs).map(Utf8PathBuf::as_path))
+        });
+    }
+}
+
+/// Get the parent directories of the given files.
+fn file_dirs(files: &BTreeSet<Utf8PathBuf>) -> BTreeSet<Utf8PathBuf> {
+    files
+        .iter()
+        .filter_map(|path| path.parent())
+        .map(Utf8Path::to_owned)
+        .collect()
+}
+
+/// The paths watched for changes, shared with the [`EventHandler`] so that it can drop events for
+/// paths we're not interested in.
+///
+/// Files are watched through their parent directories, so we get events for every other file in
+/// those directories too.
+#[derive(Debug, Clone, Default)]
+struct WatchedFiles(Arc<Mutex<WatchedFilesInner>>);
+
+#[derive(Debug, Default)]
+struct WatchedFilesInner {
+    /// The `--watch` paths, watched recursively.
+    watch: Vec<Utf8PathBuf>,
+    /// Other directories watched recursively.
+    dirs: BTreeSet<Utf8PathBuf>,
+    /// Files watched through their parent directories.
+    files: BTreeSet<Utf8PathBuf>,
+}
+
+impl WatchedFiles {
+    fn new(watch: &[NormalPath]) -> Self {
+        Self(Arc::new(Mutex::new(WatchedFilesInner {
+            watch: watch
+                .iter()
+                .map(|path| path.absolute().to_owned())
+                .collect(),
+            ..Default::default()
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WatchedFilesInner> {
+        self.0.lock().expect("Watched files lock is poisoned")
+    }
+
+    /// Set the watched directories (in addition to the `--watch` paths) and files.
+    fn set(&self, dirs: BTreeSet<Utf8PathBuf>, files: BTreeSet<Utf8PathBuf>) {
+        let mut inner = self.lock();
+        inner.dirs = dirs;
+        inner.files = files;
+    }
+
+    /// Get the watched directories, other than the `--watch` paths.
+    fn dirs(&self) -> BTreeSet<Utf8PathBuf> {
+        self.lock().dirs.clone()
+    }
+
+    /// Get the files watched through their parent directories.
+    fn files(&self) -> BTreeSet<Utf8PathBuf> {
+        self.lock().files.clone()
+    }
+
+    /// Is the given path only seen because it's next to a watched file?
+    fn is_unwatched(&self, path: &Path) -> bool {
+        let inner = self.lock();
+        path.parent().is_some_and(|parent| {
+            inner
+                .files
+                .iter()
+                .any(|file| file.parent().is_some_and(|dir| dir == parent))
+        }) && !inner.files.iter().any(|file| file == path)
+            && !inner
+                .watch
+                .iter()
+                .chain(&inner.dirs)
+                .any(|dir| path.starts_with(dir))
+    }
+}
+
+/// A batch of file events from the debouncer.
+struct EventBatch {
+    events: BTreeSet<FileEvent>,
+    /// Did the Git `HEAD` or `ORIG_HEAD` change?
+    git_activity: bool,
 }
 
 struct EventHandler {
     handle: Handle,
-    ghci_sender: mpsc::Sender<WatcherEvent>,
+    batch_sender: mpsc::Sender<EventBatch>,
     shutdown: ShutdownHandle,
+    content_hashes: Option<ContentHashes>,
+    git_dir: Option<GitDir>,
+    ignore_files: Option<IgnoreFiles>,
+    watched_files: WatchedFiles,
 }
 
 impl EventHandler {
-    async fn handle_event_async(&self, event: DebounceEventResult) {
+    async fn handle_event_async(&mut self, event: DebounceEventResult) {
         if let Err(err) = self.handle_event_inner(event).await {
             tracing::error!("{err:?}");
             let _ = self.shutdown.request_shutdown();
         }
     }
 
-    async fn handle_event_inner(&self, event: DebounceEventResult) -> eyre::Result<()> {
-        let events = match event {
+    async fn handle_event_inner(&mut self, event: DebounceEventResult) -> eyre::Result<()> {
+        let mut events = match event {
             Ok(events) => events,
             Err(errors) => {
                 let mut fatal_error = false;
@@ -161,16 +453,31 @@ impl EventHandler {
 
         tracing::trace!(?events, "Got events");
 
-        // TODO: On Linux, sometimes we get a "new directory" event but none of the events for
-        // files inside of it. When we get new directories, we should paw through them with
-        // `walkdir` or something to check for files.
-        let events = file_events_from_action(events)?;
-        if events.is_empty() {
-            tracing::debug!("No relevant file events");
-        } else {
-            tracing::debug!(?events, "Processed events");
-            self.ghci_sender
-                .send(WatcherEvent::Reload { events })
+        // Changes in the Git directory are only used to detect bulk changes.
+        let mut git_activity = false;
+        if let Some(git_dir) = &self.git_dir {
+            for event in &mut events {
+                git_activity |= event.paths.iter().any(|path| git_dir.is_activity(path));
+                event.paths.retain(|path| !git_dir.contains(path));
+            }
+        }
+
+        for event in &mut events {
+            event
+                .paths
+                .retain(|path| !self.watched_files.is_unwatched(path));
+        }
+
+        let mut events = file_events_from_action(events, self.ignore_files.as_ref())?;
+        if let Some(content_hashes) = &self.content_hashes {
+            events = content_hashes.remove_unchanged(events);
+        }
+        if !events.is_empty() || git_activity {
+            self.batch_sender
+                .send(EventBatch {
+                    events,
+                    git_activity,
+                })
                 .await?;
         }
 
@@ -180,7 +487,8 @@ impl EventHandler {
 
 impl DebounceEventHandler for EventHandler {
     fn handle_event(&mut self, event: DebounceEventResult) {
-        self.handle.block_on(self.handle_event_async(event))
+        let handle = self.handle.clone();
+        handle.block_on(self.handle_event_async(event))
     }
 }
 
@@ -108,6 +108,7 @@ pub struct Span {
 }
 
 impl Span {
+    /// Construct a span with the given name and no fields.
     #[cfg(test)]
     pub fn new(name: impl Display) -> Self {
         Self {
@@ -18,7 +18,7 @@ fn test_clap_markdown() {
         ///
         ///     complex_app
         #[derive(Debug, Clone, Parser)]
-        #[command(version, author, verbatim_doc_comment)]
+        #[command(version, author, verbatim_doc_comment, disable_help_subcommand = true)]
         pub struct Opts {
             /// A shell command which starts a `ghci` REPL, e.g. `ghci` or `cabal v2-repl` or similar.
             ///
@@ -46,6 +46,22 @@ fn test_clap_markdown() {
             /// Options to modify logging and error-handling behavior.
             #[command(flatten)]
             pub logging: LoggingOpts,
+
+            /// A subcommand to run.
+            #[command(subcommand)]
+            pub subcommand: Option<Subcommand>,
+        }
+
+        #[derive(Debug, Clone, clap::Subcommand)]
+        pub enum Subcommand {
+            /// Show how changes to the given paths would be handled.
+            ///
+            /// This is the long help for the subcommand.
+            Explain {
+                /// The paths to explain.
+                #[arg(value_name = "PATH", required = true)]
+                paths: Vec<String>,
+            },
         }
 
         #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
@@ -174,7 +190,7 @@ fn test_clap_markdown() {
 
         This is the short help. It goes first.
 
-        **Usage:** `ghciwatch [OPTIONS]`
+        **Usage:** `ghciwatch [OPTIONS] [COMMAND]`
 
 
 
@@ -291,5 +307,25 @@ fn test_clap_markdown() {
         </dl>
 
 
+        ## Subcommands
+
+        ### `ghciwatch explain`
+
+        Show how changes to the given paths would be handled.
+
+        This is the long help for the subcommand.
+
+        **Usage:** `ghciwatch explain <PATH>...`
+
+        <dl>
+
+        <dt><a id="PATH", href="#PATH"><code> &lt;PATH&gt;...</code></a></dt><dd>
+
+        The paths to explain
+
+        </dd>
+
+        </dl>
+
     "##]].assert_eq(&ghciwatch::clap_markdown::help_markdown::<complex_app::Opts>());
 }
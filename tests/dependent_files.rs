@@ -0,0 +1,134 @@
+use indoc::indoc;
+
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::Fs;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch` reloads modules when files they depend on at compile time change, even
+/// if they're outside the watched paths.
+#[test]
+async fn can_reload_for_dependent_files() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .before_start(|project| async move {
+            let fs = Fs::new();
+            fs.replace(
+                project.join("my-simple-package.cabal"),
+                "build-depends: base",
+                "build-depends: base, template-haskell",
+            )
+            .await?;
+            fs.replace(
+                project.join("my-simple-package.cabal"),
+                "exposed-modules: MyLib",
+                "exposed-modules: MyLib, Embedded",
+            )
+            .await?;
+            fs.write(project.join("data/message.txt"), "Hello").await?;
+            fs.write(
+                project.join("src/Embedded.hs"),
+                indoc!(
+                    r#"
+                    {-# LANGUAGE TemplateHaskell #-}
+                    module Embedded (message) where
+
+                    import Language.Haskell.TH.Syntax (addDependentFile, lift, runIO)
+
+                    message :: String
+                    message = $(do
+                      addDependentFile "data/message.txt"
+                      contents <- runIO (readFile "data/message.txt")
+                      lift contents)
+                    "#
+                ),
+            )
+            .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .write(session.path("data/message.txt"), "Goodbye")
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(
+            BaseMatcher::message("Needs reload for dependent file")
+                .with_field("module", "src/Embedded.hs"),
+        )
+        .await
+        .expect("ghciwatch reloads modules when their dependent files change");
+
+    session
+        .wait_for_log(BaseMatcher::message("^Compiling$").with_field("module", "Embedded"))
+        .await
+        .expect("ghci recompiles modules when their dependent files change");
+}
+
+/// Test that `ghciwatch` recompiles modules when files they read at compile time change, even if
+/// GHC doesn't track the files with `addDependentFile`.
+#[test]
+async fn can_recompile_for_untracked_dependent_files() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .before_start(|project| async move {
+            let fs = Fs::new();
+            fs.replace(
+                project.join("my-simple-package.cabal"),
+                "build-depends: base",
+                "build-depends: base, template-haskell",
+            )
+            .await?;
+            fs.replace(
+                project.join("my-simple-package.cabal"),
+                "exposed-modules: MyLib",
+                "exposed-modules: MyLib, Embedded",
+            )
+            .await?;
+            fs.write(project.join("data/message.txt"), "Hello").await?;
+            fs.write(
+                project.join("src/Embedded.hs"),
+                indoc!(
+                    r#"
+                    {-# LANGUAGE TemplateHaskell #-}
+                    module Embedded (message) where
+
+                    import Language.Haskell.TH.Syntax (lift, runIO)
+
+                    message :: String
+                    message = $(runIO (readFile "data/message.txt") >>= lift)
+                    "#
+                ),
+            )
+            .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .write(session.path("data/message.txt"), "Goodbye")
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log("Forcing recompilation for changed files which GHC doesn't track")
+        .await
+        .expect("ghciwatch forces recompilation for untracked dependent files");
+
+    session
+        .wait_for_log(BaseMatcher::message("^Compiling$").with_field("module", "Embedded"))
+        .await
+        .expect("ghci recompiles modules when their untracked dependent files change");
+}
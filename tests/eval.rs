@@ -314,3 +314,407 @@ async fn eval_can_handle_non_haskell_files() {
         .await
         .expect("ghciwatch handles non-Haskell files in eval mode");
 }
+
+/// Test that `ghciwatch --eval-doctests` checks the output of `-- >>>` examples and writes
+/// mismatches to the error log.
+#[test]
+async fn can_eval_doctests() {
+    let module_path = "src/MyLib.hs";
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--eval-doctests", "--errors", error_path])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    indoc!(
+                        r#"
+
+                        -- >>> example ++ example
+                        -- "exampleexample"
+                        "#
+                    ),
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+    let error_path = session.path(error_path);
+
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch didn't start in time");
+
+    session
+        .fs()
+        .replace(&module_path, r#"-- "exampleexample""#, r#"-- "example""#)
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message(
+            r"MyLib.hs:\d+:\d+: -- >>> example \+\+ example output doesn't match",
+        ))
+        .await
+        .expect("ghciwatch checks doctest output");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert_eq!(
+        error_contents,
+        indoc!(
+            r#"
+            All good (1 module)
+            src/MyLib.hs:6:8: error:
+                Eval output doesn't match: example ++ example
+                Expected:
+                  "example"
+                But got:
+                  "exampleexample"
+            "#
+        )
+    );
+}
+
+/// Test that `ghciwatch --update-eval-output` rewrites the expected output of eval commands.
+#[test]
+async fn can_update_expected_eval_output() {
+    let module_path = "src/MyLib.hs";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--update-eval-output"])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    "\n-- $> example ++ example\n-- <$\n",
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+
+    session
+        .wait_for_startup_log("Updated output of 1 eval commands")
+        .await
+        .expect("ghciwatch updates expected output");
+
+    let contents = session.fs().read(&module_path).await.unwrap();
+    assert!(
+        contents.ends_with("-- $> example ++ example\n-- <$ \"exampleexample\"\n"),
+        "Expected output is updated: {contents:?}"
+    );
+}
+
+/// Test that `ghciwatch --write-eval-output` writes eval output into the file without triggering
+/// a reload.
+#[test]
+async fn can_write_eval_output() {
+    let module_path = "src/MyLib.hs";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--write-eval-output"])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(path.join(module_path), "\n-- $> example ++ example\n")
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+
+    session
+        .wait_for_startup_log("Updated output of 1 eval commands")
+        .await
+        .expect("ghciwatch writes eval output");
+
+    let contents = session.fs().read(&module_path).await.unwrap();
+    assert!(
+        contents.ends_with("-- $> example ++ example\n-- <$ \"exampleexample\"\n"),
+        "Eval output is written: {contents:?}"
+    );
+
+    session
+        .wait_for_log("Ignoring change written by ghciwatch")
+        .await
+        .expect("ghciwatch ignores its own changes");
+}
+
+/// Test that `ghciwatch --write-eval-output` doesn't rewrite a file whose eval output is already
+/// up to date.
+#[test]
+async fn does_not_rewrite_unchanged_eval_output() {
+    let module_path = "src/MyLib.hs";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--write-eval-output"])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    "\n-- $> example ++ example\n-- <$ \"exampleexample\"\n",
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+    let modified = std::fs::metadata(&module_path).unwrap().modified().unwrap();
+
+    session
+        .wait_for_startup_log(
+            BaseMatcher::message(r"Eval output in .*MyLib.hs is up to date")
+                .but_not(BaseMatcher::message("Updated output of")),
+        )
+        .await
+        .expect("ghciwatch doesn't rewrite the file");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch didn't start in time");
+
+    assert_eq!(
+        std::fs::metadata(&module_path).unwrap().modified().unwrap(),
+        modified,
+        "File isn't rewritten"
+    );
+}
+
+/// Test that `ghciwatch --write-eval-output` can evaluate a file again after writing its eval
+/// output, even though it ignores the change it wrote.
+#[test]
+async fn can_eval_commands_twice_after_writing_output() {
+    let module_path = "src/MyLib.hs";
+    let model_path = "src/my_model.persistentmodels";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--enable-eval",
+            "--write-eval-output",
+            "--eval-all",
+            "--reload-glob",
+            "**/*.persistentmodels",
+        ])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(path.join(module_path), "\n-- $> example ++ example\n")
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let model_path = session.path(model_path);
+
+    session
+        .wait_for_startup_log("Updated output of 1 eval commands")
+        .await
+        .expect("ghciwatch writes eval output");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch didn't start in time");
+
+    session.fs().touch(&model_path).await.unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message(r"MyLib.hs:\d+:\d+: -- \$> example \+\+ example")
+                .but_not(BaseMatcher::message("changed during eval")),
+        )
+        .await
+        .expect("ghciwatch evals commands again");
+    session
+        .wait_for_log(
+            BaseMatcher::reload_completes().but_not(BaseMatcher::message("changed during eval")),
+        )
+        .await
+        .expect("ghciwatch doesn't think the file changed during eval");
+}
+
+/// Test that `ghciwatch` only runs eval commands in modules affected by a reload, unless
+/// `--eval-all` is given.
+#[test]
+async fn eval_skips_unaffected_modules() {
+    let module_path = "src/MyLib.hs";
+    let model_path = "src/my_model.persistentmodels";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--reload-glob", "**/*.persistentmodels"])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(path.join(module_path), "\n-- $> example ++ example\n")
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+    let model_path = session.path(model_path);
+
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch didn't start in time");
+
+    let eval_message = BaseMatcher::message(r"MyLib.hs:\d+:\d+: -- \$> example \+\+ example");
+
+    // Nothing is recompiled, so the eval commands are skipped.
+    session.clear_events();
+    session.fs().touch(&model_path).await.unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message("Skipped eval commands in modules not affected by this reload")
+                .but_not(eval_message.clone()),
+        )
+        .await
+        .expect("ghciwatch skips eval commands");
+
+    // `MyLib` is recompiled, so its eval commands are run.
+    session.clear_events();
+    session.fs().append(&module_path, "\n").await.unwrap();
+    session
+        .wait_for_log(eval_message)
+        .await
+        .expect("ghciwatch evals commands");
+}
+
+/// Test that `ghciwatch --eval-results-file` writes the results of eval commands as JSON.
+#[test]
+async fn can_write_eval_results() {
+    let module_path = "src/MyLib.hs";
+    let results_path = "eval-results.json";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--eval-results-file", results_path])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    "\n-- $> example ++ example\n-- $> head ([] :: [Int])\n",
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let results_path = session.path(results_path);
+
+    session
+        .wait_for_startup_log(BaseMatcher::message("Eval result").with_field("status", "exception"))
+        .await
+        .expect("ghciwatch logs eval results");
+    session
+        .wait_for_log("Wrote eval results")
+        .await
+        .expect("ghciwatch writes eval results");
+
+    let results = session.fs().read(&results_path).await.unwrap();
+    let results: serde_json::Value = serde_json::from_str(&results).unwrap();
+    let results = results.as_array().expect("eval results are an array");
+    assert_eq!(results.len(), 2, "{results:?}");
+    assert_eq!(results[0]["command"], "example ++ example");
+    assert_eq!(results[0]["line"], 6);
+    assert_eq!(results[0]["column"], 7);
+    assert_eq!(
+        results[0]["output"].as_str().map(str::trim_end),
+        Some("\"exampleexample\"")
+    );
+    assert_eq!(results[0]["status"], "ok");
+    assert_eq!(results[1]["command"], "head ([] :: [Int])");
+    assert_eq!(results[1]["status"], "exception");
+}
+
+/// Test that `ghciwatch --eval-timeout` interrupts slow eval commands, reports them in the error
+/// log, and runs the remaining eval commands.
+#[test]
+async fn can_time_out_eval_commands() {
+    let module_path = "src/MyLib.hs";
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--enable-eval",
+            "--eval-timeout",
+            "1s",
+            "--errors",
+            error_path,
+        ])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    indoc!(
+                        "
+
+                        -- $> import Control.Concurrent (threadDelay)
+                        -- $> threadDelay 100000000
+                        -- $> example ++ example
+                        "
+                    ),
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+
+    session
+        .wait_for_startup_log(BaseMatcher::message(
+            r"MyLib.hs:\d+:\d+: -- \$> threadDelay 100000000 timed out after 1.00s",
+        ))
+        .await
+        .expect("ghciwatch times out eval commands");
+    session
+        .wait_for_log(BaseMatcher::message("Read line").with_field("line", "exampleexample"))
+        .await
+        .expect("ghciwatch runs the remaining eval commands");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert_eq!(
+        error_contents,
+        indoc!(
+            "
+            All good (1 module)
+            src/MyLib.hs:7:7: error:
+                Eval timed out after 1.00s: threadDelay 100000000
+            "
+        )
+    );
+}
+
+/// Test that `ghciwatch --eval-line-marker` evaluates eval commands with a custom marker.
+#[test]
+async fn can_eval_commands_with_custom_markers() {
+    let module_path = "src/MyLib.hs";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--enable-eval", "--eval-line-marker", "-- eval:"])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(path.join(module_path), "\n-- eval: example ++ example\n")
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session
+        .wait_for_startup_log(
+            BaseMatcher::message("Read line").with_field("line", "exampleexample"),
+        )
+        .await
+        .expect("ghciwatch evals commands");
+}
@@ -0,0 +1,47 @@
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch explain` prints how changes to paths would be handled, including paths
+/// excluded by a negated `--restart-glob`, and then exits.
+#[test]
+async fn can_explain_paths() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--restart-glob",
+            "config/**",
+            "--restart-glob",
+            "!config/local.yaml",
+            "explain",
+            "src/MyLib.hs",
+            "src/MyModule.hs",
+            "config/settings.yaml",
+            "config/local.yaml",
+            "README.md",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    for explanation in [
+        "src/MyLib.hs: reload (Haskell source file)",
+        "src/MyModule.hs: ignored (removed Haskell source file which isn't loaded)",
+        "config/settings.yaml: restart (matches `--restart-glob config/**`)",
+        "config/local.yaml: ignored (matches `--restart-glob !config/local.yaml`)",
+        "README.md: ignored (not a Haskell source file and no globs match)",
+    ] {
+        session
+            .wait_for_log(
+                BaseMatcher::message("^Explained path$")
+                    .with_field("explanation", &format!("^{}$", regex::escape(explanation))),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("ghciwatch explains {explanation:?}: {err:?}"));
+    }
+
+    let status = session
+        .wait_until_exit()
+        .await
+        .expect("ghciwatch exits after explaining");
+    assert!(status.success(), "ghciwatch exits successfully: {status}");
+}
@@ -55,6 +55,51 @@ async fn can_skip_reload_for_ignore_glob() {
         .expect("ghciwatch reloads when a `.persistentmodels` file is created");
 }
 
+/// Test that `ghciwatch --respect-ignore-files` skips reloading when a file ignored by a
+/// `.gitignore` file is changed.
+#[test]
+async fn can_skip_reload_for_ignore_files() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_arg("--respect-ignore-files")
+        .before_start(|path| async move {
+            Fs::new()
+                .write(path.join("src/.gitignore"), "generated/\n")
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .touch(session.path("src/generated/MyModule.hs"))
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(
+            BaseMatcher::message("Ignoring change to ignored path")
+                .with_field("path", "src/generated/MyModule.hs"),
+        )
+        .await
+        .expect("ghciwatch ignores changes to ignored paths");
+
+    session
+        .fs()
+        .touch(session.path("src/MyModule.hs"))
+        .await
+        .unwrap();
+
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads when a file which isn't ignored is changed");
+}
+
 /// Test that `ghciwatch` can restart when a file matching a `--restart-glob` is changed.
 #[test]
 async fn can_restart_on_custom_file_change() {
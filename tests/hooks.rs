@@ -5,6 +5,7 @@ use test_harness::BaseMatcher;
 use test_harness::Fs;
 use test_harness::GhciWatch;
 use test_harness::GhciWatchBuilder;
+use test_harness::Matcher;
 use test_harness::SpanMatcher;
 
 /// Test that `ghciwatch` can run its lifecycle hooks.
@@ -116,6 +117,276 @@ async fn can_run_hooks() {
     ghci_hook(&mut session, "after-restart", "2").await;
 }
 
+/// Test that `ghciwatch` runs hooks around tests and after compilation succeeds or fails.
+#[test]
+async fn can_run_test_and_compilation_hooks() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--success-ghci",
+            "putStrLn \"success-1\"",
+            "--failure-shell",
+            "touch failure-1",
+            "--before-test-shell",
+            "touch before-test-1",
+            "--test-ghci",
+            "putStrLn \"test-1\"",
+            "--after-test-ghci",
+            "putStrLn \"after-test-1\"",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session.wait_until_ready().await.unwrap();
+
+    let module_path = session.path("src/MyLib.hs");
+
+    session.fs().touch(&module_path).await.unwrap();
+
+    ghci_hook(&mut session, "success", "1").await;
+    shell_hook(&mut session, "before-test", "1").await;
+    ghci_hook(&mut session, "test", "1").await;
+    ghci_hook(&mut session, "after-test", "1").await;
+
+    session
+        .fs()
+        .replace(&module_path, "example :: String", "example :: ()")
+        .await
+        .unwrap();
+
+    shell_hook(&mut session, "failure", "1").await;
+}
+
+/// Test that `ghciwatch` expands placeholders in hook commands.
+#[test]
+async fn can_expand_placeholders_in_hooks() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--after-reload-shell",
+            "touch {event}-{changed_modules}",
+            "--after-reload-ghci",
+            "putStrLn \"{event}-{changed_modules}\"",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session.wait_until_ready().await.unwrap();
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+
+    shell_hook(&mut session, "after-reload", "MyLib").await;
+    ghci_hook(&mut session, "after-reload", "MyLib").await;
+}
+
+/// Test that `ghciwatch` can select the tests to run for the modules recompiled in a reload.
+#[test]
+async fn can_select_tests() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--test-ghci",
+            "System.Environment.getArgs >>= print",
+            "--select-tests",
+            // Make `MyLib` a spec module (for the module `Lib`).
+            "--spec-module-format",
+            "My{module}",
+            "--reload-glob",
+            "**/*.txt",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session
+        .wait_for_startup_log("Selected all tests")
+        .await
+        .unwrap();
+    session
+        .wait_for_startup_log(BaseMatcher::message("Read line").with_field("line", r"^\[\]$"))
+        .await
+        .unwrap();
+
+    session.wait_until_ready().await.unwrap();
+
+    session
+        .fs()
+        .append(session.path("src/MyLib.hs"), "\n-- Changed\n")
+        .await
+        .unwrap();
+
+    session.wait_for_log("Selected tests").await.unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message("Read line").with_field("line", r#"^\["--match","/MyLib/"\]$"#),
+        )
+        .await
+        .unwrap();
+
+    // Reloads which don't recompile any modules run all the tests.
+    session
+        .fs()
+        .write(session.path("src/notes.txt"), "Changed")
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log("No modules recompiled, selected all tests")
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message("Read line").with_field("line", r"^\[\]$"))
+        .await
+        .unwrap();
+}
+
+/// Test that `ghciwatch` skips the tests when a reload recompiles modules, but no spec modules.
+#[test]
+async fn can_skip_tests_without_affected_specs() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--test-ghci",
+            "System.Environment.getArgs >>= print",
+            "--select-tests",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session.wait_until_ready().await.unwrap();
+
+    session
+        .fs()
+        .append(session.path("src/MyLib.hs"), "\n-- Changed\n")
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log("No affected specs, skipping tests")
+        .await
+        .unwrap();
+}
+
+/// Test that `ghciwatch` interrupts `ghci` hooks and kills shell hooks which run longer than
+/// their timeouts, reports the timeouts in the error log, and keeps working afterwards.
+#[test]
+async fn can_time_out_hooks() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--errors",
+            "ghcid.txt",
+            "--test-ghci",
+            "Control.Concurrent.threadDelay 60000000",
+            "--test-timeout",
+            "1s",
+            "--after-reload-shell",
+            "sleep 60",
+            "--after-reload-timeout",
+            "1s",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session
+        .wait_for_startup_log(
+            BaseMatcher::message("test command timed out")
+                .with_field("command", "threadDelay 60000000"),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_for_startup_log("Interrupted ghci")
+        .await
+        .unwrap();
+    let error_contents = session
+        .fs()
+        .read(session.path("ghcid.txt"))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert!(
+        error_contents.contains("test command timed out after"),
+        "Error log should include the timed out hook: {error_contents}"
+    );
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(BaseMatcher::message("sleep timed out after"))
+        .await
+        .unwrap();
+    // A timed out shell hook fails the reload, so the tests aren't run.
+    session
+        .wait_for_log(
+            BaseMatcher::message("^Reloading failed in")
+                .but_not(BaseMatcher::message("test command timed out")),
+        )
+        .await
+        .unwrap();
+    let error_contents = session
+        .fs()
+        .read(session.path("ghcid.txt"))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert!(
+        error_contents.contains("after-reload command timed out after"),
+        "Error log should include the timed out hook: {error_contents}"
+    );
+}
+
+/// Test that `ghciwatch` can cancel `async:` shell hooks which are superseded by the same event
+/// occurring again.
+#[test]
+async fn can_cancel_superseded_async_hooks() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--after-reload-shell",
+            "async:sleep 60",
+            "--cancel-superseded-async-hooks",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session.wait_until_ready().await.unwrap();
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message("Running after-reload command").with_field("command", "sleep 60"),
+        )
+        .await
+        .unwrap();
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message("Cancelling superseded after-reload command")
+                .with_field("command", "sleep 60"),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message("sleep was cancelled after"))
+        .await
+        .unwrap();
+}
+
 fn hook_timeout(session: &GhciWatch, hook: &str) -> Duration {
     if hook.ends_with("-startup") || hook.ends_with("-restart") {
         session.startup_timeout
@@ -270,6 +541,80 @@ async fn hooks_can_observe_error_log() {
         .unwrap();
 }
 
+/// Test that `ghciwatch` can parse diagnostics from shell hooks into the error log.
+#[test]
+async fn hooks_can_report_diagnostics() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--errors",
+            "ghcid.txt",
+            "--after-reload-shell",
+            "ghc-diagnostics:echo 'src/MyLib.hs:1:1: warning: Found a lint'",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session.wait_until_ready().await.unwrap();
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(
+            BaseMatcher::message("Parsed diagnostics from after-reload command")
+                .with_field("diagnostics", "1"),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message("All good!"))
+        .await
+        .unwrap();
+
+    let error_contents = session.fs().read(session.path("ghcid.txt")).await.unwrap();
+    assert!(
+        error_contents.contains("src/MyLib.hs:1:1: warning: Found a lint"),
+        "Error log should contain the hook's diagnostic:\n{error_contents}"
+    );
+}
+
+/// Test that diagnostics from test hooks, which run after the error log is written, are added to
+/// the error log with paths relative to it.
+#[test]
+async fn test_hooks_can_report_diagnostics() {
+    let error_path = "logs/ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--errors",
+            error_path,
+            "--after-test-shell",
+            "ghc-diagnostics:echo 'src/MyLib.hs:1:1: warning: Found a lint'",
+        ])
+        .before_start(|project| async move { Fs::new().create_dir(project.join("logs")).await })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+
+    session
+        .wait_for_startup_log(
+            BaseMatcher::message("Parsed diagnostics from after-test command")
+                .with_field("diagnostics", "1"),
+        )
+        .await
+        .unwrap();
+    session.wait_until_ready().await.unwrap();
+
+    let error_contents = session.fs().read(session.path(error_path)).await.unwrap();
+    assert!(
+        error_contents.contains("../src/MyLib.hs:1:1: warning: Found a lint"),
+        "Error log should contain the hook's diagnostic relative to it:\n{error_contents}"
+    );
+}
+
 fn shell_requote(cmd: &str) -> String {
     shell_words::join(shell_words::split(cmd).unwrap())
 }
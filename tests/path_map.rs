@@ -0,0 +1,125 @@
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::Fs;
+use test_harness::GhciWatchBuilder;
+use test_harness::Matcher;
+
+/// Test that `ghciwatch --path-map` maps the paths `ghci` prints to the paths `ghciwatch` sees,
+/// and maps paths back when adding modules.
+///
+/// The project is symlinked into another directory, which stands in for a container's mount of
+/// the project; `ghci` is only told about modules by their paths in that directory.
+#[test]
+async fn can_map_paths() {
+    let container_dir = tempfile::tempdir().unwrap();
+    let container = container_dir.path().join("workspace");
+    let container_str = container.to_str().unwrap().to_owned();
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--errors",
+            error_path,
+            "--path-map",
+            &format!("{container_str}=."),
+        ])
+        .with_ghc_arg(format!("{container_str}/src/MyModule.hs"))
+        .before_start(move |project| {
+            let container = container.clone();
+            async move {
+                std::os::unix::fs::symlink(&project, &container)?;
+                Fs::new()
+                    .write(
+                        project.join("src/MyModule.hs"),
+                        "module MyModule (example) where\n\
+                        \n\
+                        example :: String\n\
+                        example = \"example\"\n",
+                    )
+                    .await
+            }
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+
+    // Paths in compiler output are mapped to the host.
+    session
+        .wait_for_startup_log(
+            BaseMatcher::message("^Compiling$")
+                .with_field("module", "MyModule")
+                .with_field("path", "/simple/src/MyModule.hs$"),
+        )
+        .await
+        .expect("ghciwatch maps compiled module paths");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    // Paths in `:show targets` are mapped to the host, so the module is reloaded rather than
+    // added again.
+    session
+        .fs()
+        .touch(session.path("src/MyModule.hs"))
+        .await
+        .unwrap();
+    session
+        .wait_for_log(
+            BaseMatcher::message("^Needs reload$")
+                .with_field("path", "^src/MyModule.hs$")
+                .but_not(BaseMatcher::message("^Needs add$")),
+        )
+        .await
+        .expect("ghciwatch maps target paths");
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads");
+
+    // New modules are added with the path `ghci` sees.
+    session
+        .fs()
+        .write(
+            session.path("src/MyOther.hs"),
+            "module MyOther (other) where\n\nother :: String\nother = \"other\"\n",
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch adds the new module");
+    session
+        .wait_for_log(
+            BaseMatcher::message("^Compiling$")
+                .with_field("module", "MyOther")
+                .with_field("path", "/simple/src/MyOther.hs$"),
+        )
+        .await
+        .expect("ghciwatch adds modules by their mapped paths");
+
+    // Diagnostics are written to the error log with host paths.
+    session
+        .fs()
+        .append(session.path("src/MyOther.hs"), "broken = \n")
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    session
+        .wait_for_log(BaseMatcher::reload_completes())
+        .await
+        .expect("ghciwatch finishes reloading");
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes the error log");
+    assert!(
+        error_contents.starts_with("src/MyOther.hs:") && !error_contents.contains("workspace"),
+        "Error log has host paths: {error_contents:?}"
+    );
+}
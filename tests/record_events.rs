@@ -0,0 +1,112 @@
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::Fs;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch` can record the file events it sends to `ghci`.
+#[test]
+async fn can_record_events() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--record-events", "events.jsonl"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .write(
+            session.path("src/My/Module.hs"),
+            "module My.Module (x) where\nx :: Int\nx = 1\n",
+        )
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds the new module");
+
+    let events = session
+        .fs()
+        .read(session.path("events.jsonl"))
+        .await
+        .unwrap();
+    assert!(
+        events.contains(r#"{"modify":"src/My/Module.hs"}"#),
+        "Recorded events should include the new module: {events}"
+    );
+}
+
+/// Test that `ghciwatch` doesn't record events for the events file itself, which would cause
+/// more events.
+#[test]
+async fn does_not_record_events_file() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--record-events", "events.jsonl", "--watch", "."])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::reload_completes())
+        .await
+        .expect("ghciwatch reloads");
+
+    let events = session
+        .fs()
+        .read(session.path("events.jsonl"))
+        .await
+        .unwrap();
+    assert!(
+        !events.contains("events.jsonl"),
+        "Recorded events shouldn't include the events file: {events}"
+    );
+}
+
+/// Test that `ghciwatch` can replay recorded file events instead of watching for changes.
+#[test]
+async fn can_replay_events() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--replay-events", "events.jsonl"])
+        .before_start(|project| async move {
+            let fs = Fs::new();
+            fs.write(
+                project.join("src/My/Module.hs"),
+                "module My.Module (x) where\nx :: Int\nx = 1\n",
+            )
+            .await?;
+            fs.write(
+                project.join("events.jsonl"),
+                "{\"time_ms\":0,\"events\":[{\"modify\":\"src/My/Module.hs\"}]}\n",
+            )
+            .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_started()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds the replayed module");
+    session
+        .assert_logged_or_wait(BaseMatcher::message("^Finished replaying file events$"))
+        .await
+        .expect("ghciwatch finishes replaying events");
+}
@@ -3,6 +3,7 @@ use indoc::indoc;
 use test_harness::test;
 use test_harness::BaseMatcher;
 use test_harness::GhciWatch;
+use test_harness::GhciWatchBuilder;
 
 /// Test that `ghciwatch` can start up and then reload on changes.
 #[test]
@@ -87,3 +88,84 @@ async fn can_reload_after_error() {
         .await
         .unwrap();
 }
+
+/// Test that `ghciwatch` doesn't reload when a file is saved without changing its contents.
+#[test]
+async fn can_skip_reload_for_unchanged_file() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_arg("--skip-unchanged")
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(
+            BaseMatcher::message("Skipping unchanged file").with_field("path", "MyLib.hs$"),
+        )
+        .await
+        .expect("ghciwatch skips unchanged files");
+
+    session
+        .fs()
+        .append(session.path("src/MyLib.hs"), "\nhello = 1\n")
+        .await
+        .unwrap();
+
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads when a file's contents change");
+}
+
+/// Test that `ghciwatch --hold-bulk-changes` handles many files changing at once as a single bulk
+/// change.
+#[test]
+async fn can_hold_bulk_changes() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--hold-bulk-changes", "--bulk-change-threshold", "3"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    for module in ["A", "B", "C"] {
+        session
+            .fs()
+            .write(
+                session.path(format!("src/{module}.hs")),
+                format!("module {module} where\n"),
+            )
+            .await
+            .unwrap();
+    }
+
+    session
+        .wait_for_log(BaseMatcher::message(
+            "files changed at once, waiting for changes to finish$",
+        ))
+        .await
+        .expect("ghciwatch detects bulk changes");
+
+    session
+        .wait_for_log(BaseMatcher::message("^Changes finished, 3 files changed$"))
+        .await
+        .expect("ghciwatch finishes bulk changes");
+
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds the new modules");
+}
@@ -51,3 +51,41 @@ async fn can_compile_renamed_module() {
         .await
         .unwrap();
 }
+
+/// Test that `ghciwatch` adds the modules in a directory which is moved into place, even if it
+/// doesn't get events for the files inside it.
+#[test]
+async fn can_add_modules_in_new_directory() {
+    let mut session = GhciWatch::new("tests/data/simple")
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    // Create the directory outside of `src` so we don't get events for the files inside it.
+    session
+        .fs()
+        .write(
+            session.path("new-modules/NewModule.hs"),
+            "module NewModule (x) where\nx :: Int\nx = 1\n",
+        )
+        .await
+        .unwrap();
+    session
+        .fs()
+        .rename(session.path("new-modules"), session.path("src/NewModules"))
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds modules in new directories");
+
+    session
+        .wait_for_log(BaseMatcher::compilation_succeeded())
+        .await
+        .unwrap();
+}
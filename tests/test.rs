@@ -44,3 +44,109 @@ async fn can_run_test_suite_on_reload() {
     "#]]
     .assert_eq(&error_contents);
 }
+
+/// Test that `ghciwatch` parses `hspec` results from the test suite's output and writes failing
+/// tests to the error log.
+#[test]
+async fn can_parse_test_results() {
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--test-ghci",
+            r#"putStr "Failures:\n\n  src/MyLib.hs:3:1:\n  1) MyLib fails\n       oops\n\n2 examples, 1 failure\n""#,
+            "--errors",
+            error_path,
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .expect("Can touch file");
+
+    session
+        .wait_for_log(
+            BaseMatcher::message("Test failed: MyLib fails").with_field("path", "^src/MyLib.hs$"),
+        )
+        .await
+        .expect("ghciwatch logs the failing test");
+    session
+        .wait_for_log(
+            BaseMatcher::message("Finished running tests")
+                .with_field("passed", "^1$")
+                .with_field("failed", "^1$"),
+        )
+        .await
+        .expect("ghciwatch logs the test results");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    expect![[r#"
+        All good (1 module)
+        src/MyLib.hs:3:1: error:
+            Test failed: MyLib fails
+            oops
+    "#]]
+    .assert_eq(&error_contents);
+}
+
+/// Test that `ghciwatch --interrupt-tests` interrupts a running test suite when files change.
+#[test]
+async fn can_interrupt_tests() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--no-interrupt-reloads",
+            "--interrupt-tests",
+            // Only run the slow tests after reloads, so that startup finishes quickly.
+            "--before-reload-ghci",
+            r#"System.Environment.setEnv "SLOW_TESTS" "1""#,
+            "--test-ghci",
+            r#"System.Environment.lookupEnv "SLOW_TESTS" >>= mapM_ (const (Control.Concurrent.threadDelay 60000000))"#,
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .expect("Can touch file");
+    session
+        .wait_for_log(BaseMatcher::message("Running test command"))
+        .await
+        .expect("ghciwatch runs the test suite");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .expect("Can touch file");
+    session
+        .wait_for_log("Interrupted ghci")
+        .await
+        .expect("ghciwatch interrupts the test suite");
+    session
+        .wait_for_log(BaseMatcher::reload_completes())
+        .await
+        .expect("ghciwatch reloads after interrupting the tests");
+}
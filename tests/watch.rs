@@ -0,0 +1,86 @@
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixStream;
+
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::Fs;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch` can watch the module search paths from `:show paths`.
+#[test]
+async fn can_watch_search_paths() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--watch-search-paths"])
+        .before_start(|project| async move {
+            let fs = Fs::new();
+            fs.create_dir(project.join("test")).await?;
+            fs.write(project.join(".ghci"), ":set -itest\n").await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .assert_logged_or_wait(
+            BaseMatcher::message("Watching search path").with_field("path", "/test$"),
+        )
+        .await
+        .expect("ghciwatch watches search paths");
+
+    session
+        .fs()
+        .write(
+            session.path("test/MyTest.hs"),
+            "module MyTest (x) where\nx :: Int\nx = 1\n",
+        )
+        .await
+        .unwrap();
+
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds modules in search paths");
+}
+
+/// Test that `ghciwatch` can reload in response to save notifications from an editor, without
+/// watching the filesystem.
+#[test]
+async fn can_reload_from_save_notifications() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--save-socket", "ghciwatch.sock", "--no-file-watcher"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_started()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .assert_logged_or_wait(BaseMatcher::message("^Listening for save notifications$"))
+        .await
+        .expect("ghciwatch listens for save notifications");
+
+    session
+        .fs()
+        .write(
+            session.path("src/My/Module.hs"),
+            "module My.Module (x) where\nx :: Int\nx = 1\n",
+        )
+        .await
+        .unwrap();
+
+    let mut socket = UnixStream::connect(session.path("ghciwatch.sock"))
+        .await
+        .expect("ghciwatch accepts connections");
+    socket.write_all(b"src/My/Module.hs\n").await.unwrap();
+    socket.shutdown().await.unwrap();
+
+    session
+        .wait_for_log(BaseMatcher::ghci_add())
+        .await
+        .expect("ghciwatch adds the saved module");
+}